@@ -0,0 +1,195 @@
+/// Modo de anti-aliasing del `SoftwareRenderer`.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum AntiAliasing {
+    Off,
+    /// FXAA (post-proceso sobre el buffer final de 8 bits)
+    Fxaa,
+}
+
+impl AntiAliasing {
+    pub fn next(self) -> Self {
+        match self {
+            AntiAliasing::Off => AntiAliasing::Fxaa,
+            AntiAliasing::Fxaa => AntiAliasing::Off,
+        }
+    }
+}
+
+/// Contraste mínimo (absoluto y relativo al pixel más claro) para tratar un pixel como borde
+const EDGE_MIN: f32 = 0.0312;
+const EDGE_THRESHOLD: f32 = 0.125;
+/// Peso del suavizado sub-pixel (0 = solo bordes largos)
+const SUBPIXEL: f32 = 0.75;
+/// Pasos máximos al buscar el final de un borde en cada sentido
+const SEARCH_STEPS: i32 = 12;
+
+fn luma(px: &[u8]) -> f32 {
+    (0.299 * px[0] as f32 + 0.587 * px[1] as f32 + 0.114 * px[2] as f32) / 255.0
+}
+
+/// FXAA sobre un buffer RGBA8 de `width * height`. Lee de `src` y escribe en
+/// `dst` (mismo tamaño). Sigue la estructura de FXAA 3.11 "quality": detecta
+/// el borde por contraste de luma, decide si es horizontal o vertical, busca
+/// sus extremos y mezcla con el vecino al otro lado del borde.
+pub fn fxaa(width: usize, height: usize, src: &[u8], dst: &mut [u8]) {
+    dst.copy_from_slice(src);
+    if width < 3 || height < 3 {
+        return;
+    }
+    let lumas: Vec<f32> = src.chunks_exact(4).map(luma).collect();
+    let l = |x: i32, y: i32| -> f32 {
+        let x = x.clamp(0, width as i32 - 1) as usize;
+        let y = y.clamp(0, height as i32 - 1) as usize;
+        lumas[y * width + x]
+    };
+
+    for y in 0..height as i32 {
+        for x in 0..width as i32 {
+            let lm = l(x, y);
+            let ln = l(x, y - 1);
+            let ls = l(x, y + 1);
+            let le = l(x + 1, y);
+            let lw = l(x - 1, y);
+            let max_l = lm.max(ln).max(ls).max(le).max(lw);
+            let min_l = lm.min(ln).min(ls).min(le).min(lw);
+            let range = max_l - min_l;
+            if range < EDGE_MIN.max(max_l * EDGE_THRESHOLD) {
+                continue;
+            }
+
+            let lnw = l(x - 1, y - 1);
+            let lne = l(x + 1, y - 1);
+            let lsw = l(x - 1, y + 1);
+            let lse = l(x + 1, y + 1);
+
+            // Suavizado sub-pixel: cuánto se aleja el pixel de la media de su vecindario
+            let avg = (2.0 * (ln + ls + le + lw) + lnw + lne + lsw + lse) / 12.0;
+            let sub = ((avg - lm).abs() / range).clamp(0.0, 1.0);
+            let sub = sub * sub * (3.0 - 2.0 * sub);
+            let sub_offset = sub * sub * SUBPIXEL;
+
+            // Borde horizontal (cambio en Y) o vertical (cambio en X)
+            let edge_h = (ln + ls - 2.0 * lm).abs() * 2.0 + (lne + lse - 2.0 * le).abs() + (lnw + lsw - 2.0 * lw).abs();
+            let edge_v = (le + lw - 2.0 * lm).abs() * 2.0 + (lne + lnw - 2.0 * ln).abs() + (lse + lsw - 2.0 * ls).abs();
+            let horizontal = edge_h >= edge_v;
+
+            // Lado del borde con mayor gradiente
+            let (l_neg, l_pos) = if horizontal { (ln, ls) } else { (lw, le) };
+            let grad_neg = l_neg - lm;
+            let grad_pos = l_pos - lm;
+            let (step, l_side) = if grad_neg.abs() >= grad_pos.abs() { (-1, l_neg) } else { (1, l_pos) };
+            let gradient_scaled = 0.25 * grad_neg.abs().max(grad_pos.abs());
+            let local_avg = 0.5 * (lm + l_side);
+
+            // Luma a medio pixel hacia el lado del borde, desplazado `k` a lo largo del borde
+            let edge_luma = |k: i32| -> f32 {
+                if horizontal {
+                    0.5 * (l(x + k, y) + l(x + k, y + step))
+                } else {
+                    0.5 * (l(x, y + k) + l(x + step, y + k))
+                }
+            };
+
+            let mut d_neg = SEARCH_STEPS;
+            let mut end_neg = 0.0;
+            for k in 1..=SEARCH_STEPS {
+                end_neg = edge_luma(-k) - local_avg;
+                if end_neg.abs() >= gradient_scaled {
+                    d_neg = k;
+                    break;
+                }
+            }
+            let mut d_pos = SEARCH_STEPS;
+            let mut end_pos = 0.0;
+            for k in 1..=SEARCH_STEPS {
+                end_pos = edge_luma(k) - local_avg;
+                if end_pos.abs() >= gradient_scaled {
+                    d_pos = k;
+                    break;
+                }
+            }
+
+            // Solo se mezcla si el extremo más cercano "gira" hacia este pixel
+            let m_smaller = lm < local_avg;
+            let (dist, end) = if d_neg < d_pos { (d_neg, end_neg) } else { (d_pos, end_pos) };
+            let edge_offset = if (end < 0.0) != m_smaller {
+                0.5 - dist as f32 / (d_neg + d_pos) as f32
+            } else {
+                0.0
+            };
+            let offset = edge_offset.max(sub_offset);
+            if offset <= 0.0 {
+                continue;
+            }
+
+            let (nx, ny) = if horizontal { (x, y + step) } else { (x + step, y) };
+            let nx = nx.clamp(0, width as i32 - 1) as usize;
+            let ny = ny.clamp(0, height as i32 - 1) as usize;
+            let i = (y as usize * width + x as usize) * 4;
+            let n = (ny * width + nx) * 4;
+            for c in 0..3 {
+                let a = src[i + c] as f32;
+                let b = src[n + c] as f32;
+                dst[i + c] = (a + (b - a) * offset).round() as u8;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const W: usize = 64;
+    const H: usize = 64;
+
+    /// Semiplano blanco sobre negro con pendiente suave (el peor caso de
+    /// "escalera"). Con `samples > 1` devuelve la cobertura real por pixel.
+    fn slanted_edge(samples: usize) -> Vec<u8> {
+        let inside = |x: f32, y: f32| y > 0.23 * x + 20.0;
+        let mut img = vec![0u8; W * H * 4];
+        for y in 0..H {
+            for x in 0..W {
+                let mut hits = 0;
+                for sy in 0..samples {
+                    for sx in 0..samples {
+                        let px = x as f32 + (sx as f32 + 0.5) / samples as f32;
+                        let py = y as f32 + (sy as f32 + 0.5) / samples as f32;
+                        if inside(px, py) {
+                            hits += 1;
+                        }
+                    }
+                }
+                let v = (255 * hits / (samples * samples)) as u8;
+                let i = (y * W + x) * 4;
+                img[i..i + 4].copy_from_slice(&[v, v, v, 255]);
+            }
+        }
+        img
+    }
+
+    fn mean_abs_error(a: &[u8], b: &[u8]) -> f32 {
+        let sum: u32 = a.iter().zip(b).map(|(x, y)| (*x as i32 - *y as i32).unsigned_abs()).sum();
+        sum as f32 / a.len() as f32
+    }
+
+    #[test]
+    fn fxaa_brings_hard_edge_closer_to_coverage() {
+        let aliased = slanted_edge(1);
+        let reference = slanted_edge(16);
+        let mut smoothed = vec![0u8; aliased.len()];
+        fxaa(W, H, &aliased, &mut smoothed);
+
+        let before = mean_abs_error(&aliased, &reference);
+        let after = mean_abs_error(&smoothed, &reference);
+        assert!(after < before * 0.7, "FXAA error {} vs aliased {}", after, before);
+    }
+
+    #[test]
+    fn fxaa_leaves_flat_image_untouched() {
+        let flat = vec![90u8; W * H * 4];
+        let mut out = vec![0u8; flat.len()];
+        fxaa(W, H, &flat, &mut out);
+        assert_eq!(flat, out);
+    }
+}
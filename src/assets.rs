@@ -0,0 +1,323 @@
+use std::collections::HashMap;
+use std::env;
+use std::error::Error;
+use std::fmt;
+use std::fs;
+use std::marker::PhantomData;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant, SystemTime};
+
+use crate::mesh_cache::load_cached;
+use crate::object::ObjMesh;
+use crate::texture::TextureCPU;
+
+/// Variable de entorno con la carpeta de assets (tiene prioridad sobre la búsqueda)
+pub const ROOT_ENV: &str = "SPACETRAVEL_ASSETS";
+
+/// Referencia a un asset cargado en `Assets`. Sigue siendo válida tras una
+/// recarga: apunta al hueco, no al contenido.
+pub struct Handle<T> {
+    index: usize,
+    _kind: PhantomData<fn() -> T>,
+}
+
+impl<T> Handle<T> {
+    fn new(index: usize) -> Self {
+        Self { index, _kind: PhantomData }
+    }
+}
+
+impl<T> Clone for Handle<T> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+impl<T> Copy for Handle<T> {}
+impl<T> PartialEq for Handle<T> {
+    fn eq(&self, other: &Self) -> bool {
+        self.index == other.index
+    }
+}
+impl<T> Eq for Handle<T> {}
+impl<T> fmt::Debug for Handle<T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Handle({})", self.index)
+    }
+}
+
+/// Fecha de modificación y tamaño: si cambia cualquiera, el archivo cambió.
+type Stamp = Option<(SystemTime, u64)>;
+
+fn stamp(path: &Path) -> Stamp {
+    let meta = fs::metadata(path).ok()?;
+    Some((meta.modified().ok()?, meta.len()))
+}
+
+/// Sello de `path` seguido de los de sus dependencias.
+fn stamps(path: &Path, deps: &[PathBuf]) -> Vec<Stamp> {
+    std::iter::once(path).chain(deps.iter().map(PathBuf::as_path)).map(stamp).collect()
+}
+
+struct Slot<T> {
+    path: PathBuf,
+    value: T,
+    /// Sello del archivo principal seguido de los de sus dependencias
+    stamps: Vec<Stamp>,
+}
+
+/// Colección de assets de un tipo, con un hueco por archivo.
+struct Store<T> {
+    slots: Vec<Slot<T>>,
+    by_path: HashMap<PathBuf, usize>,
+    /// Otros archivos de los que depende un asset (`.mtl`, texturas, `.bin`)
+    deps: fn(&T) -> &[PathBuf],
+}
+
+impl<T> Store<T> {
+    fn new(deps: fn(&T) -> &[PathBuf]) -> Self {
+        Self { slots: Vec::new(), by_path: HashMap::new(), deps }
+    }
+
+    fn find(&self, path: &Path) -> Option<Handle<T>> {
+        self.by_path.get(path).map(|&i| Handle::new(i))
+    }
+
+    fn insert(&mut self, path: PathBuf, value: T) -> Handle<T> {
+        let stamps = stamps(&path, (self.deps)(&value));
+        self.by_path.insert(path.clone(), self.slots.len());
+        self.slots.push(Slot { path, value, stamps });
+        Handle::new(self.slots.len() - 1)
+    }
+
+    /// Recarga con `load` los archivos cuyo sello (o el de alguna de sus
+    /// dependencias) cambió. Si la recarga falla se avisa y se conserva la
+    /// versión anterior.
+    fn reload_changed<E: fmt::Display>(&mut self, load: impl Fn(&Path) -> Result<T, E>, changed: &mut Vec<Handle<T>>) {
+        let deps = self.deps;
+        for (i, slot) in self.slots.iter_mut().enumerate() {
+            let now = stamps(&slot.path, deps(&slot.value));
+            if now == slot.stamps || now[0].is_none() {
+                continue;
+            }
+            slot.stamps = now;
+            match load(&slot.path) {
+                Ok(value) => {
+                    // Las dependencias pueden haber cambiado con la recarga
+                    slot.stamps = stamps(&slot.path, deps(&value));
+                    slot.value = value;
+                    changed.push(Handle::new(i));
+                }
+                Err(e) => eprintln!("aviso: no se pudo recargar {}: {}", slot.path.display(), e),
+            }
+        }
+    }
+}
+
+/// Lo que cambió en disco desde el último `Assets::poll`.
+#[derive(Debug, Default)]
+pub struct Changes {
+    pub meshes: Vec<Handle<ObjMesh>>,
+    pub textures: Vec<Handle<TextureCPU>>,
+    /// Archivos registrados con `Assets::watch` (escenas, configuración)
+    pub files: Vec<PathBuf>,
+}
+
+impl Changes {
+    #[cfg(test)]
+    pub fn is_empty(&self) -> bool {
+        self.meshes.is_empty() && self.textures.is_empty() && self.files.is_empty()
+    }
+}
+
+/// Gestor de assets: resuelve rutas contra una carpeta raíz, guarda cada
+/// malla y textura una sola vez (pedir dos veces la misma ruta devuelve el
+/// mismo `Handle`) y, con `hot_reload`, recarga lo que cambie en disco.
+pub struct Assets {
+    root: PathBuf,
+    meshes: Store<ObjMesh>,
+    textures: Store<TextureCPU>,
+    watched: Vec<(PathBuf, Stamp)>,
+    pub hot_reload: bool,
+    /// Tiempo mínimo entre dos comprobaciones de `poll`
+    pub poll_interval: Duration,
+    last_poll: Option<Instant>,
+}
+
+impl Assets {
+    pub fn new(root: impl Into<PathBuf>) -> Self {
+        Self {
+            root: root.into(),
+            meshes: Store::new(|m| &m.dependencies),
+            textures: Store::new(|_| &[]),
+            watched: Vec::new(),
+            hot_reload: true,
+            poll_interval: Duration::from_millis(500),
+            last_poll: None,
+        }
+    }
+
+    /// Busca la carpeta de assets: `SPACETRAVEL_ASSETS`, luego `assets/` en el
+    /// directorio actual y luego junto al ejecutable o en sus carpetas padre
+    /// (así funciona `target/debug/SpaceTravel` lanzado desde cualquier sitio).
+    pub fn locate() -> Self {
+        if let Ok(dir) = env::var(ROOT_ENV) {
+            return Self::new(dir);
+        }
+        let local = PathBuf::from("assets");
+        if local.is_dir() {
+            return Self::new(local);
+        }
+        if let Ok(exe) = env::current_exe()
+            && let Some(found) = exe.ancestors().skip(1).map(|d| d.join("assets")).find(|d| d.is_dir())
+        {
+            return Self::new(found);
+        }
+        Self::new(local)
+    }
+
+    /// Ruta completa de `rel` dentro de la raíz.
+    pub fn path(&self, rel: &str) -> PathBuf {
+        self.root.join(rel)
+    }
+
+    /// Carga (o devuelve la ya cargada) la malla `rel` (OBJ o glTF, con caché binario).
+    pub fn mesh(&mut self, rel: &str) -> Result<Handle<ObjMesh>, Box<dyn Error>> {
+        let path = self.path(rel);
+        if let Some(h) = self.meshes.find(&path) {
+            return Ok(h);
+        }
+        let mesh = load_cached(&path.to_string_lossy())?;
+        Ok(self.meshes.insert(path, mesh))
+    }
+
+    /// Como `mesh`, pero si no se puede cargar avisa y usa `fallback`. La ruta
+    /// queda vigilada: si el archivo aparece o se arregla, se recarga.
+    pub fn mesh_or(&mut self, rel: &str, fallback: impl FnOnce() -> ObjMesh) -> Handle<ObjMesh> {
+        self.mesh(rel).unwrap_or_else(|e| {
+            eprintln!("{}; usando una malla generada", e);
+            let path = self.path(rel);
+            let h = self.meshes.insert(path, fallback());
+            // Sin sello: cualquier versión que aparezca cuenta como cambio
+            self.meshes.slots[h.index].stamps = vec![None];
+            h
+        })
+    }
+
+    pub fn texture(&mut self, rel: &str) -> Result<Handle<TextureCPU>, String> {
+        let path = self.path(rel);
+        if let Some(h) = self.textures.find(&path) {
+            return Ok(h);
+        }
+        let tex = TextureCPU::load(&path.to_string_lossy())?;
+        Ok(self.textures.insert(path, tex))
+    }
+
+    pub fn get_mesh(&self, h: Handle<ObjMesh>) -> &ObjMesh {
+        &self.meshes.slots[h.index].value
+    }
+
+    pub fn get_mesh_mut(&mut self, h: Handle<ObjMesh>) -> &mut ObjMesh {
+        &mut self.meshes.slots[h.index].value
+    }
+
+    pub fn get_texture(&self, h: Handle<TextureCPU>) -> &TextureCPU {
+        &self.textures.slots[h.index].value
+    }
+
+    /// Ruta relativa a la raíz con la que se cargó una textura.
+    pub fn texture_path(&self, h: Handle<TextureCPU>) -> &Path {
+        let path = &self.textures.slots[h.index].path;
+        path.strip_prefix(&self.root).unwrap_or(path)
+    }
+
+    /// Vigila un archivo que no es malla ni textura (escenas, configuración);
+    /// sus cambios aparecen en `Changes::files`.
+    pub fn watch(&mut self, rel: &str) -> PathBuf {
+        let path = self.path(rel);
+        if !self.watched.iter().any(|(p, _)| *p == path) {
+            self.watched.push((path.clone(), stamp(&path)));
+        }
+        path
+    }
+
+    /// Comprueba (como mucho cada `poll_interval`) si algún asset cambió en
+    /// disco y lo recarga. Llamar una vez por frame.
+    pub fn poll(&mut self) -> Changes {
+        let mut changes = Changes::default();
+        if !self.hot_reload || self.last_poll.is_some_and(|t| t.elapsed() < self.poll_interval) {
+            return changes;
+        }
+        self.last_poll = Some(Instant::now());
+
+        self.meshes.reload_changed(|p| load_cached(&p.to_string_lossy()), &mut changes.meshes);
+        self.textures.reload_changed(|p| TextureCPU::load(&p.to_string_lossy()), &mut changes.textures);
+        for (path, old) in self.watched.iter_mut() {
+            let now = stamp(path);
+            if now != *old {
+                *old = now;
+                changes.files.push(path.clone());
+            }
+        }
+        for h in &changes.meshes {
+            eprintln!("recargado {}", self.meshes.slots[h.index].path.display());
+        }
+        for h in &changes.textures {
+            eprintln!("recargado {}", self.textures.slots[h.index].path.display());
+        }
+        changes
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::TempDir;
+    use glam::Vec3;
+
+    #[test]
+    fn meshes_are_cached_by_path_and_hot_reloaded() {
+        let root = TempDir::new("assets");
+        root.write("models/tri.obj", "v 0 0 0\nv 1 0 0\nv 0 1 0\nf 1 2 3\n");
+
+        let mut assets = Assets::new(root.path());
+        assets.poll_interval = Duration::ZERO;
+        let h = assets.mesh("models/tri.obj").unwrap();
+        assert_eq!(assets.mesh("models/tri.obj").unwrap(), h);
+        assert_eq!(assets.get_mesh(h).indices.len(), 1);
+        assert!(assets.poll().is_empty());
+
+        root.write("models/tri.obj", "v 0 0 0\nv 1 0 0\nv 1 1 0\nv 0 1 0\nf 1 2 3 4\n");
+        let changes = assets.poll();
+        assert_eq!(changes.meshes, vec![h]);
+        assert_eq!(assets.get_mesh(h).indices.len(), 2);
+
+        // Un archivo roto no sustituye a la versión buena
+        root.write("models/tri.obj", "v 0 0 0\nf 1 2 3\n");
+        assert!(assets.poll().meshes.is_empty());
+        assert_eq!(assets.get_mesh(h).indices.len(), 2);
+
+        // Malla que falta: se usa la alternativa hasta que el archivo aparece
+        let missing = assets.mesh_or("models/nueva.obj", || ObjMesh::cube(1.0));
+        assert_eq!(assets.get_mesh(missing).indices.len(), 12);
+        root.write("models/nueva.obj", "v 0 0 0\nv 1 0 0\nv 0 1 0\nf 1 2 3\n");
+        assert_eq!(assets.poll().meshes, vec![missing]);
+        assert_eq!(assets.get_mesh(missing).indices.len(), 1);
+    }
+
+    #[test]
+    fn editing_a_material_reloads_its_mesh() {
+        let root = TempDir::new("assets_mtl");
+        root.write("models/tri.obj", "mtllib tri.mtl\nv 0 0 0\nv 1 0 0\nv 0 1 0\nusemtl Rojo\nf 1 2 3\n");
+        root.write("models/tri.mtl", "newmtl Rojo\nKd 1 0 0\n");
+
+        let mut assets = Assets::new(root.path());
+        assets.poll_interval = Duration::ZERO;
+        let h = assets.mesh("models/tri.obj").unwrap();
+        assert_eq!(assets.get_mesh(h).materials[0].diffuse, Vec3::X);
+        assert!(assets.poll().is_empty());
+
+        root.write("models/tri.mtl", "newmtl Rojo\nKd 0.0 0.0 1.0\n");
+        assert_eq!(assets.poll().meshes, vec![h]);
+        assert_eq!(assets.get_mesh(h).materials[0].diffuse, Vec3::Z);
+    }
+}
@@ -1,4 +1,4 @@
-use glam::{Vec3, Mat4};
+use glam::{Vec3, Vec4, Mat4};
 use crate::utils::*;
 
 pub struct Camera {
@@ -64,3 +64,57 @@ impl Camera {
         perspective(self.fov_y.to_radians(), self.aspect, 0.1, 2000.0)
     }
 }
+
+/// Los seis planos del frustum de la cámara, con la normal hacia dentro.
+pub struct Frustum {
+    planes: [Vec4; 6],
+}
+
+impl Frustum {
+    /// Extrae los planos de una matriz vista-proyección (Gribb/Hartmann).
+    pub fn from_matrix(m: Mat4) -> Self {
+        let (r0, r1, r2, r3) = (m.row(0), m.row(1), m.row(2), m.row(3));
+        // glam::perspective_rh usa profundidad 0..1, así que el plano cercano es z >= 0
+        let mut planes = [r3 + r0, r3 - r0, r3 + r1, r3 - r1, r2, r3 - r2];
+        for p in planes.iter_mut() {
+            let len = p.truncate().length();
+            if len > 0.0 {
+                *p /= len;
+            }
+        }
+        Self { planes }
+    }
+
+    /// `false` solo si la esfera queda entera fuera de algún plano.
+    pub fn intersects_sphere(&self, center: Vec3, radius: f32) -> bool {
+        self.planes
+            .iter()
+            .all(|p| p.truncate().dot(center) + p.w >= -radius)
+    }
+}
+
+impl Camera {
+    pub fn frustum(&self) -> Frustum {
+        Frustum::from_matrix(self.proj_matrix() * self.view_matrix())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn frustum_classifies_spheres() {
+        let cam = Camera::new(Vec3::new(0.0, 0.0, 10.0), Vec3::ZERO);
+        let frustum = cam.frustum();
+        // Dentro, fuera por un lado y cruzando ese mismo borde
+        assert!(frustum.intersects_sphere(Vec3::ZERO, 1.0));
+        assert!(!frustum.intersects_sphere(Vec3::new(0.0, 20.0, 0.0), 1.0));
+        assert!(frustum.intersects_sphere(Vec3::new(0.0, 20.0, 0.0), 15.0));
+        // Detrás de la cámara: fuera salvo que llegue hasta delante
+        assert!(!frustum.intersects_sphere(Vec3::new(0.0, 0.0, 15.0), 1.0));
+        assert!(frustum.intersects_sphere(Vec3::new(0.0, 0.0, 15.0), 6.0));
+        // Más allá del plano lejano
+        assert!(!frustum.intersects_sphere(Vec3::new(0.0, 0.0, -3000.0), 1.0));
+    }
+}
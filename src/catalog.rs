@@ -0,0 +1,249 @@
+use glam::Vec3;
+use std::collections::HashMap;
+use std::fmt;
+use std::fs;
+
+use crate::skybox::cube_face;
+use crate::starfield::{encode_faces, magnitude_brightness, splat, temperature_color};
+use crate::texture::TextureCPU;
+
+/// Catálogo incluido, relativo a la raíz de assets
+pub const STARS_FILE: &str = "stars/bright_stars.csv";
+/// Líneas de constelaciones, relativo a la raíz de assets
+pub const LINES_FILE: &str = "stars/constellations.csv";
+
+/// Color lineal de las líneas de constelaciones
+const LINE_COLOR: Vec3 = Vec3::new(0.02, 0.035, 0.07);
+/// Hueco (radianes) entre el final de una línea y su estrella
+const LINE_GAP: f32 = 0.012;
+/// B-V que se supone si una fila no lo trae (el del Sol)
+const DEFAULT_BV: f32 = 0.65;
+
+/// Error al leer un catálogo o su archivo de líneas.
+#[derive(Debug)]
+pub enum CatalogError {
+    Io { path: String, source: std::io::Error },
+    Parse { path: String, line: usize, reason: String },
+}
+
+impl fmt::Display for CatalogError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            CatalogError::Io { path, source } => write!(f, "No se pudo leer {}: {}", path, source),
+            CatalogError::Parse { path, line, reason } => write!(f, "{}:{}: {}", path, line, reason),
+        }
+    }
+}
+
+impl std::error::Error for CatalogError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            CatalogError::Io { source, .. } => Some(source),
+            CatalogError::Parse { .. } => None,
+        }
+    }
+}
+
+/// Una estrella del catálogo (coordenadas ecuatoriales J2000).
+#[derive(Clone, Debug, PartialEq)]
+pub struct CatalogStar {
+    pub name: String,
+    /// Ascensión recta, en horas
+    pub ra_hours: f32,
+    /// Declinación, en grados
+    pub dec_deg: f32,
+    /// Magnitud visual aparente
+    pub vmag: f32,
+    /// Índice de color B-V (negativo = azul, > 1.5 = rojo)
+    pub bv: f32,
+}
+
+impl CatalogStar {
+    /// Dirección unitaria en el mundo: el polo norte celeste es +Y y la
+    /// ascensión recta 0 es +X, creciendo hacia el este (hacia -Z).
+    pub fn direction(&self) -> Vec3 {
+        let ra = (self.ra_hours * 15.0).to_radians();
+        let dec = self.dec_deg.to_radians();
+        Vec3::new(dec.cos() * ra.cos(), dec.sin(), -dec.cos() * ra.sin())
+    }
+
+    /// Temperatura efectiva a partir de B-V (fórmula de Ballesteros).
+    pub fn temperature(&self) -> f32 {
+        4600.0 * (1.0 / (0.92 * self.bv + 1.7) + 1.0 / (0.92 * self.bv + 0.62))
+    }
+}
+
+/// Estrellas reales y, opcionalmente, las líneas de sus constelaciones.
+#[derive(Clone, Debug, Default)]
+pub struct StarCatalog {
+    pub stars: Vec<CatalogStar>,
+    /// Pares de índices en `stars`
+    pub lines: Vec<(usize, usize)>,
+    /// Filas que se saltaron o completaron al leer (`archivo:línea: motivo`);
+    /// quien carga el catálogo decide cómo mostrarlas
+    pub warnings: Vec<String>,
+}
+
+/// Divide una línea de CSV sin comentarios; `None` si está vacía.
+fn csv_fields(raw: &str) -> Option<Vec<&str>> {
+    let line = raw.split('#').next().unwrap_or("").trim();
+    (!line.is_empty()).then(|| line.split(',').map(str::trim).collect())
+}
+
+impl StarCatalog {
+    /// Lado en texels de cada cara del cubo del cielo real
+    pub const FACE_SIZE: u32 = 1024;
+
+    pub fn load(path: &str) -> Result<Self, CatalogError> {
+        let text = fs::read_to_string(path).map_err(|source| CatalogError::Io { path: path.to_string(), source })?;
+        Self::parse(&text, path)
+    }
+
+    /// CSV con cabecera `name,ra_hours,dec_deg,vmag,bv` (en cualquier orden;
+    /// se ignoran otras columnas y las líneas con `#`). Una fila sin
+    /// coordenadas o sin magnitud se salta y una sin B-V usa `DEFAULT_BV`,
+    /// ambas con un aviso en `warnings`.
+    pub fn parse(text: &str, path: &str) -> Result<Self, CatalogError> {
+        let mut rows = text.lines().enumerate().filter_map(|(n, raw)| csv_fields(raw).map(|f| (n + 1, f)));
+        let err = |line, reason: String| CatalogError::Parse { path: path.to_string(), line, reason };
+
+        let (header_line, header) = rows.next().ok_or_else(|| err(1, "archivo vacío".to_string()))?;
+        let column = |name: &str| {
+            header.iter().position(|h| h.eq_ignore_ascii_case(name)).ok_or_else(|| err(header_line, format!("falta la columna {}", name)))
+        };
+        let [name, ra, dec, vmag, bv] = [column("name")?, column("ra_hours")?, column("dec_deg")?, column("vmag")?, column("bv")?];
+
+        let mut stars = Vec::new();
+        let mut warnings = Vec::new();
+        for (line, fields) in rows {
+            // `None` si el campo está vacío
+            let value = |i: usize| -> Result<Option<f32>, CatalogError> {
+                let text = fields.get(i).ok_or_else(|| err(line, format!("se esperaban {} columnas", header.len())))?;
+                if text.is_empty() {
+                    return Ok(None);
+                }
+                text.parse().map(Some).map_err(|_| err(line, format!("número inválido '{}'", text)))
+            };
+            let star_name = fields.get(name).unwrap_or(&"").to_string();
+            let (Some(ra_hours), Some(dec_deg), Some(vmag)) = (value(ra)?, value(dec)?, value(vmag)?) else {
+                warnings.push(format!("{}:{}: '{}' sin posición o magnitud, se omite", path, line, star_name));
+                continue;
+            };
+            let bv = value(bv)?.unwrap_or_else(|| {
+                warnings.push(format!("{}:{}: '{}' sin B-V, se usa {}", path, line, star_name, DEFAULT_BV));
+                DEFAULT_BV
+            });
+            stars.push(CatalogStar { name: star_name, ra_hours, dec_deg, vmag, bv });
+        }
+        Ok(Self { stars, lines: Vec::new(), warnings })
+    }
+
+    pub fn load_lines(&mut self, path: &str) -> Result<(), CatalogError> {
+        let text = fs::read_to_string(path).map_err(|source| CatalogError::Io { path: path.to_string(), source })?;
+        self.parse_lines(&text, path)
+    }
+
+    /// Líneas `constelación,desde,hasta` entre estrellas por nombre. Si un
+    /// nombre no está en el catálogo se salta esa línea con un aviso en
+    /// `warnings`.
+    pub fn parse_lines(&mut self, text: &str, path: &str) -> Result<(), CatalogError> {
+        let by_name: HashMap<&str, usize> = self.stars.iter().enumerate().map(|(i, s)| (s.name.as_str(), i)).collect();
+        let mut lines = Vec::new();
+        // La primera fila es la cabecera
+        for (n, fields) in text.lines().enumerate().filter_map(|(n, raw)| csv_fields(raw).map(|f| (n + 1, f))).skip(1) {
+            let [_, from, to] = fields[..] else {
+                return Err(CatalogError::Parse { path: path.to_string(), line: n, reason: "se esperaban 3 columnas".to_string() });
+            };
+            match (by_name.get(from), by_name.get(to)) {
+                (Some(&a), Some(&b)) => lines.push((a, b)),
+                _ => self.warnings.push(format!("{}:{}: estrella desconocida en {} - {}", path, n, from, to)),
+            }
+        }
+        self.lines = lines;
+        Ok(())
+    }
+
+    /// Genera las seis caras del cubo (orden de `skybox::FACES`) con cada
+    /// estrella en su dirección real, más intensa y con más halo cuanto menor
+    /// es su magnitud y con el color de su B-V. Con `lines` se dibujan
+    /// también las constelaciones, algo separadas de sus estrellas.
+    pub fn bake(&self, face_size: u32, lines: bool) -> [TextureCPU; 6] {
+        let n = face_size.max(1);
+        let mut faces: [Vec<Vec3>; 6] = std::array::from_fn(|_| vec![Vec3::ZERO; (n * n) as usize]);
+
+        if lines {
+            // Arco de círculo máximo, con unos dos puntos por texel
+            let step = 1.0 / n as f32;
+            for &(a, b) in &self.lines {
+                let (from, to) = (self.stars[a].direction(), self.stars[b].direction());
+                let angle = from.angle_between(to);
+                if angle <= 2.0 * LINE_GAP {
+                    continue;
+                }
+                let axis = from.cross(to).normalize_or_zero();
+                let mut t = LINE_GAP;
+                while t < angle - LINE_GAP {
+                    let dir = glam::Quat::from_axis_angle(axis, t) * from;
+                    let (face, u, v) = cube_face(dir);
+                    splat(&mut faces[face], n, u, v, LINE_COLOR);
+                    t += step;
+                }
+            }
+        }
+        for star in &self.stars {
+            let (face, u, v) = cube_face(star.direction());
+            let color = temperature_color(star.temperature()) * magnitude_brightness(star.vmag);
+            splat(&mut faces[face], n, u, v, color);
+        }
+        let mut faces = faces.map(Some);
+        encode_faces(n, |face| faces[face].take().unwrap_or_default())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn catalog_parses_and_places_stars() {
+        let text = "# comentario\nname,ra_hours,dec_deg,vmag,bv\nPolaris,2.5303,89.264,1.98,0.60\n\
+                    Rigel,5.2423,-8.202,0.13,-0.03\nAntares,16.4901,-26.432,0.96,1.83\n";
+        let mut cat = StarCatalog::parse(text, "test.csv").unwrap();
+        assert_eq!(cat.stars.len(), 3);
+        assert!(cat.stars[0].direction().y > 0.999);
+        // Ascensión recta 6h: 90 grados al este, hacia -Z
+        let east = CatalogStar { name: String::new(), ra_hours: 6.0, dec_deg: 0.0, vmag: 0.0, bv: 0.0 };
+        assert!(east.direction().abs_diff_eq(Vec3::NEG_Z, 1e-6));
+        // Rigel azulada y caliente, Antares roja y fría
+        assert!(cat.stars[1].temperature() > 9000.0 && cat.stars[2].temperature() < 4000.0);
+
+        assert!(cat.warnings.is_empty());
+
+        cat.parse_lines("constellation,from,to\nX,Polaris,Rigel\nX,Polaris,Nadie\n", "lines.csv").unwrap();
+        assert_eq!(cat.lines, vec![(0, 1)]);
+        assert_eq!(cat.warnings, vec!["lines.csv:3: estrella desconocida en Polaris - Nadie"]);
+
+        // Campos vacíos: sin B-V se supone el del Sol, sin posición se salta la fila
+        let cat = StarCatalog::parse("name,ra_hours,dec_deg,vmag,bv\nA,1,2,3,\nB,,2,3,0.5\nC,1,2,3,0.1\n", "huecos.csv").unwrap();
+        assert_eq!(cat.stars.iter().map(|s| (s.name.as_str(), s.bv)).collect::<Vec<_>>(), vec![("A", DEFAULT_BV), ("C", 0.1)]);
+        assert_eq!(cat.warnings.len(), 2);
+        assert!(cat.warnings[0].starts_with("huecos.csv:2:") && cat.warnings[1].starts_with("huecos.csv:3:"));
+
+        match StarCatalog::parse("name,ra_hours,dec_deg,vmag,bv\nA,1,2,x,0\n", "malo.csv") {
+            Err(CatalogError::Parse { line, .. }) => assert_eq!(line, 2),
+            other => panic!("esperaba error de formato, no {:?}", other),
+        }
+        assert!(StarCatalog::parse("name,ra_hours\n", "corto.csv").is_err());
+    }
+
+    #[test]
+    fn bundled_catalog_loads() {
+        let mut cat = StarCatalog::load(&format!("assets/{}", STARS_FILE)).unwrap();
+        assert!(cat.stars.len() > 100);
+        cat.load_lines(&format!("assets/{}", LINES_FILE)).unwrap();
+        assert!(cat.lines.len() > 50);
+        assert_eq!(cat.warnings, Vec::<String>::new());
+        let sirius = cat.stars.iter().find(|s| s.name == "Sirius").unwrap();
+        assert_eq!(sirius.vmag, -1.46);
+    }
+}
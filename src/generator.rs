@@ -0,0 +1,172 @@
+use raylib::prelude::Color;
+use glam::Vec3;
+
+use crate::planet::{Moon, Planet, Ring};
+use crate::renderer::{CloudLayer, PlanetShaderKind, ShaderParams};
+use crate::utils::Rng;
+
+/// Genera un sistema planetario completo a partir de `seed`.
+/// La misma semilla produce siempre el mismo sistema (orden de llamadas al
+/// RNG fijo), así que un bug se puede reproducir con solo anotar la semilla.
+/// El primer elemento es siempre la estrella, en el origen.
+pub fn generate_system(seed: u64) -> Vec<Planet> {
+    let mut rng = Rng::new(seed);
+    let mut planets = Vec::new();
+
+    let sun_scale = rng.range(3.0, 5.5);
+    let sun_color = star_color(&mut rng);
+    planets.push(Planet::with_shader("Sol", 0.0, 0.0, 0.0, sun_scale, sun_color, PlanetShaderKind::Sun));
+
+    let count = rng.range_usize(3, 8);
+    // Espaciado geométrico tipo Titius-Bode: cada órbita es ~1.3-1.7x la anterior
+    let mut orbit = sun_scale * 2.5 + rng.range(10.0, 16.0);
+    let mut last_scale = 0.0_f32;
+
+    for i in 0..count {
+        // Planetas interiores rocosos, exteriores más grandes y fríos
+        let outer = i as f32 / count as f32;
+        let shader = pick_shader(&mut rng, outer);
+        let scale = match shader {
+            PlanetShaderKind::Gas => rng.range(2.2, 3.4),
+            PlanetShaderKind::SuperEarth => rng.range(1.7, 2.3),
+            _ => rng.range(0.9, 1.7),
+        };
+
+        // Dejar sitio a los dos planetas vecinos (y a sus anillos/lunas)
+        orbit += (last_scale + scale) * 2.0;
+        last_scale = scale;
+
+        // Kepler: la velocidad angular cae con r^1.5 (normalizada a la órbita de la Tierra)
+        let orbit_speed = 0.7 * (18.0 / orbit).powf(1.5) * rng.range(0.9, 1.1);
+        let rotation_speed = rng.range(0.5, 2.0);
+        let color = body_color(&mut rng, shader);
+
+        let mut p = Planet::with_shader(
+            &format!("Planeta-{}", i + 1),
+            orbit,
+            orbit_speed,
+            rotation_speed,
+            scale,
+            color,
+            shader,
+        );
+        p.angle_orbit = rng.range(0.0, std::f32::consts::TAU);
+        p.params = ShaderParams {
+            noise_offset: Vec3::new(rng.range(-50.0, 50.0), rng.range(-50.0, 50.0), rng.range(-50.0, 50.0)),
+            noise_scale: rng.range(0.7, 1.4),
+            clouds: None,
+        };
+
+        let cloud_chance = match shader {
+            PlanetShaderKind::Earth | PlanetShaderKind::SuperEarth => 0.8,
+            PlanetShaderKind::Ice => 0.2,
+            _ => 0.0,
+        };
+        if rng.chance(cloud_chance) {
+            let mut clouds = CloudLayer::new(rng.range(0.2, 0.6), rng.range(0.03, 0.2));
+            clouds.seed = rng.range(0.0, 100.0);
+            clouds.scale = rng.range(2.2, 4.0);
+            p.params.clouds = Some(clouds);
+        }
+
+        let ring_chance = if matches!(shader, PlanetShaderKind::Gas) { 0.6 } else { 0.1 };
+        if rng.chance(ring_chance) {
+            let inner = scale * rng.range(1.25, 1.7);
+            p.ring = Some(Ring {
+                inner,
+                outer: inner + scale * rng.range(0.8, 1.5),
+                color: Color::new(
+                    (rng.range(140.0, 220.0)) as u8,
+                    (rng.range(110.0, 190.0)) as u8,
+                    (rng.range(80.0, 160.0)) as u8,
+                    200,
+                ),
+            });
+        }
+
+        let max_moons = if matches!(shader, PlanetShaderKind::Gas) { 4 } else { 2 };
+        let moon_count = rng.range_usize(0, max_moons);
+        let mut moon_orbit = p.ring.map_or(scale * 2.0, |r| r.outer + 0.5);
+        for _ in 0..moon_count {
+            let moon_scale = rng.range(0.25, 0.3 + scale * 0.2);
+            moon_orbit += moon_scale * 2.0 + rng.range(0.8, 2.0);
+            let mut m = Moon::new(moon_orbit, rng.range(0.6, 1.6), moon_scale, Color::LIGHTGRAY);
+            m.angle_orbit = rng.range(0.0, std::f32::consts::TAU);
+            p.moons.push(m);
+        }
+
+        // La siguiente órbita se separa lo suficiente para no cruzar lunas
+        orbit += moon_orbit.max(scale * 2.5) + orbit * rng.range(0.25, 0.45);
+        planets.push(p);
+    }
+
+    planets
+}
+
+fn pick_shader(rng: &mut Rng, outer: f32) -> PlanetShaderKind {
+    let roll = rng.next_f32();
+    if outer < 0.35 {
+        if roll < 0.5 { PlanetShaderKind::Volcanic } else if roll < 0.85 { PlanetShaderKind::Earth } else { PlanetShaderKind::SuperEarth }
+    } else if outer < 0.65 {
+        if roll < 0.35 { PlanetShaderKind::Earth } else if roll < 0.6 { PlanetShaderKind::SuperEarth } else { PlanetShaderKind::Gas }
+    } else if roll < 0.6 {
+        PlanetShaderKind::Gas
+    } else {
+        PlanetShaderKind::Ice
+    }
+}
+
+fn star_color(rng: &mut Rng) -> Color {
+    match rng.range_usize(0, 3) {
+        0 => Color::new(255, 210, 120, 255), // naranja (tipo K)
+        1 => Color::new(255, 240, 200, 255), // blanco (tipo F)
+        _ => Color::YELLOW,                  // amarilla (tipo G)
+    }
+}
+
+fn body_color(rng: &mut Rng, shader: PlanetShaderKind) -> Color {
+    let jitter = |rng: &mut Rng, c: u8| (c as f32 * rng.range(0.85, 1.15)).min(255.0) as u8;
+    let base = match shader {
+        PlanetShaderKind::Earth => Color::BLUE,
+        PlanetShaderKind::SuperEarth => Color::new(200, 180, 80, 255),
+        PlanetShaderKind::Volcanic => Color::RED,
+        PlanetShaderKind::Ice => Color::SKYBLUE,
+        PlanetShaderKind::Gas => Color::BEIGE,
+        _ => Color::LIGHTGRAY,
+    };
+    Color::new(jitter(rng, base.r), jitter(rng, base.g), jitter(rng, base.b), 255)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Lo que la semilla decide de cada cuerpo, en texto comparable.
+    fn describe(planets: &[Planet]) -> Vec<String> {
+        planets
+            .iter()
+            .map(|p| {
+                let ring = p.ring.map(|r| (r.inner, r.outer, r.color));
+                let clouds = p.params.clouds.map(|c| (c.coverage, c.speed));
+                let moons: Vec<_> = p
+                    .moons
+                    .iter()
+                    .map(|m| (m.orbit_radius, m.orbit_speed, m.scale, m.color, m.shader as u8, m.angle_orbit))
+                    .collect();
+                format!(
+                    "{} {} {} {} {} {:?} {} {} {:?} {} {:?} {:?} {:?}",
+                    p.name, p.orbit_radius, p.orbit_speed, p.rotation_speed, p.scale, p.color, p.shader as u8,
+                    p.angle_orbit, p.params.noise_offset, p.params.noise_scale, clouds, ring, moons
+                )
+            })
+            .collect()
+    }
+
+    #[test]
+    fn same_seed_same_system() {
+        let a = describe(&generate_system(42));
+        assert_eq!(a, describe(&generate_system(42)));
+        assert_ne!(a, describe(&generate_system(43)));
+        assert!(a.len() >= 4, "la estrella y al menos tres planetas");
+    }
+}
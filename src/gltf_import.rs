@@ -0,0 +1,286 @@
+use glam::{Mat3, Mat4, Vec2, Vec3};
+use std::fmt;
+use std::path::Path;
+
+use gltf::image::Format as GltfFormat;
+use gltf::mesh::Mode;
+use gltf::material::AlphaMode;
+
+use crate::material::Material;
+use crate::object::{MaterialRange, ObjMesh};
+use crate::texture::{Filter, Format, TextureCPU};
+
+/// Error al importar un glTF.
+#[derive(Debug)]
+pub enum GltfError {
+    Import { path: String, source: gltf::Error },
+    /// El archivo no tiene ninguna escena que dibujar
+    NoScene { path: String },
+}
+
+impl fmt::Display for GltfError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            GltfError::Import { path, source } => write!(f, "No se pudo importar {}: {}", path, source),
+            GltfError::NoScene { path } => write!(f, "{}: no hay ninguna escena", path),
+        }
+    }
+}
+
+impl std::error::Error for GltfError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            GltfError::Import { source, .. } => Some(source),
+            GltfError::NoScene { .. } => None,
+        }
+    }
+}
+
+/// Importa un glTF 2.0 (`.gltf` con sus `.bin`, o `.glb`) como una sola
+/// malla: recorre la jerarquía de nodos de la escena por defecto aplicando
+/// las transformaciones acumuladas, así un modelo de varias piezas queda
+/// montado. Cada primitiva es un tramo con su material; las UV se pasan al
+/// convenio de OBJ (V hacia arriba) para que sirvan igual que las de `.mtl`.
+pub fn load_gltf(path: &str) -> Result<ObjMesh, GltfError> {
+    let (doc, buffers, images) = gltf::import(path).map_err(|source| GltfError::Import { path: path.to_string(), source })?;
+    let scene = doc
+        .default_scene()
+        .or_else(|| doc.scenes().next())
+        .ok_or_else(|| GltfError::NoScene { path: path.to_string() })?;
+
+    let mut builder = Builder {
+        materials: doc.materials().map(|m| convert_material(&m, &images, path)).collect(),
+        ..Default::default()
+    };
+    for node in scene.nodes() {
+        builder.visit(&node, Mat4::IDENTITY, &buffers, path);
+    }
+    let mut mesh = builder.finish();
+
+    // Buffers e imágenes en archivos aparte (no embebidos ni en el `.glb`)
+    let dir = Path::new(path).parent().unwrap_or(Path::new(""));
+    let external = |uri: &str| (!uri.starts_with("data:")).then(|| dir.join(uri));
+    let buffer_files = doc.buffers().filter_map(|b| match b.source() {
+        gltf::buffer::Source::Uri(uri) => external(uri),
+        gltf::buffer::Source::Bin => None,
+    });
+    let image_files = doc.images().filter_map(|i| match i.source() {
+        gltf::image::Source::Uri { uri, .. } => external(uri),
+        gltf::image::Source::View { .. } => None,
+    });
+    mesh.dependencies = buffer_files.chain(image_files).collect();
+    Ok(mesh)
+}
+
+#[derive(Default)]
+struct Builder {
+    vertices: Vec<Vec3>,
+    normals: Vec<Vec3>,
+    uvs: Vec<Vec2>,
+    all_normals: bool,
+    all_uvs: bool,
+    indices: Vec<[usize; 3]>,
+    materials: Vec<Material>,
+    ranges: Vec<MaterialRange>,
+}
+
+impl Builder {
+    fn visit(&mut self, node: &gltf::Node, parent: Mat4, buffers: &[gltf::buffer::Data], path: &str) {
+        let world = parent * Mat4::from_cols_array_2d(&node.transform().matrix());
+        if let Some(mesh) = node.mesh() {
+            for prim in mesh.primitives() {
+                self.add_primitive(&prim, world, buffers, path);
+            }
+        }
+        for child in node.children() {
+            self.visit(&child, world, buffers, path);
+        }
+    }
+
+    fn add_primitive(&mut self, prim: &gltf::Primitive, world: Mat4, buffers: &[gltf::buffer::Data], path: &str) {
+        if prim.mode() != Mode::Triangles {
+            eprintln!("{}: aviso: primitiva {:?} ignorada (solo triángulos)", path, prim.mode());
+            return;
+        }
+        let reader = prim.reader(|b| buffers.get(b.index()).map(|d| &d.0[..]));
+        let Some(positions) = reader.read_positions() else {
+            eprintln!("{}: aviso: primitiva sin POSITION ignorada", path);
+            return;
+        };
+
+        let first = self.vertices.is_empty();
+        let base = self.vertices.len();
+        self.vertices.extend(positions.map(|p| world.transform_point3(Vec3::from(p))));
+        let count = self.vertices.len() - base;
+
+        let normal_matrix = Mat3::from_mat4(world).inverse().transpose();
+        let normals = reader.read_normals();
+        self.all_normals = (first || self.all_normals) && normals.is_some();
+        match normals {
+            Some(n) => self.normals.extend(n.map(|n| (normal_matrix * Vec3::from(n)).normalize_or_zero())),
+            None => self.normals.resize(self.vertices.len(), Vec3::ZERO),
+        }
+        let uvs = reader.read_tex_coords(0);
+        self.all_uvs = (first || self.all_uvs) && uvs.is_some();
+        match uvs {
+            Some(t) => self.uvs.extend(t.into_f32().map(|[u, v]| Vec2::new(u, 1.0 - v))),
+            None => self.uvs.resize(self.vertices.len(), Vec2::ZERO),
+        }
+
+        let local: Vec<usize> = match reader.read_indices() {
+            Some(i) => i.into_u32().map(|i| i as usize).collect(),
+            None => (0..count).collect(),
+        };
+        // Una escala negativa invierte el sentido de las caras
+        let mirrored = world.determinant() < 0.0;
+        let start = self.indices.len();
+        for t in local.chunks_exact(3) {
+            if t.iter().any(|&i| i >= count) {
+                continue;
+            }
+            let [a, b, c] = [t[0] + base, t[1] + base, t[2] + base];
+            self.indices.push(if mirrored { [a, c, b] } else { [a, b, c] });
+        }
+        self.ranges.push(MaterialRange { material: prim.material().index(), triangles: start..self.indices.len() });
+    }
+
+    fn finish(mut self) -> ObjMesh {
+        if !self.all_normals {
+            self.normals.clear();
+        }
+        if !self.all_uvs {
+            self.uvs.clear();
+        }
+        let mut mesh = ObjMesh::new(self.vertices, self.indices);
+        mesh.normals = self.normals;
+        mesh.uvs = self.uvs;
+        if self.ranges.iter().any(|r| r.material.is_some()) {
+            mesh.materials = self.materials;
+            mesh.ranges = self.ranges;
+        }
+        mesh
+    }
+}
+
+/// Material PBR (metallic-roughness) aproximado con el Blinn-Phong de `.mtl`:
+/// el especular sale del factor metálico y el exponente de la rugosidad.
+fn convert_material(m: &gltf::Material, images: &[gltf::image::Data], path: &str) -> Material {
+    let pbr = m.pbr_metallic_roughness();
+    let [r, g, b, a] = pbr.base_color_factor();
+    let mut out = Material::new(m.name().unwrap_or(""));
+    out.diffuse = Vec3::new(r, g, b);
+    out.specular = Vec3::splat(0.04).lerp(out.diffuse, pbr.metallic_factor());
+    let rough = pbr.roughness_factor().max(0.05);
+    out.shininess = (2.0 / rough.powi(4) - 2.0).clamp(1.0, 512.0);
+    out.emissive = Vec3::from(m.emissive_factor());
+    out.opacity = if m.alpha_mode() == AlphaMode::Blend { a } else { 1.0 };
+    out.diffuse_map = pbr
+        .base_color_texture()
+        .and_then(|info| images.get(info.texture().source().index()))
+        .and_then(|img| {
+            let tex = texture_from_image(img);
+            if tex.is_none() {
+                eprintln!("{}: aviso: formato de textura {:?} no soportado", path, img.format);
+            }
+            tex
+        });
+    out
+}
+
+fn texture_from_image(img: &gltf::image::Data) -> Option<TextureCPU> {
+    let format = match img.format {
+        GltfFormat::R8 => Format::R8,
+        GltfFormat::R8G8B8 => Format::Rgb8,
+        GltfFormat::R8G8B8A8 => Format::Rgba8,
+        _ => return None,
+    };
+    let mut tex = TextureCPU::new(img.width, img.height, format, img.pixels.clone()).ok()?;
+    // Es color base aunque venga en un canal
+    tex.set_srgb(true);
+    tex.filter = Filter::Trilinear;
+    Some(tex)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::TempDir;
+
+    /// Un triángulo en un nodo hijo escalado x2 bajo un padre trasladado +10 en X.
+    const JSON: &str = r#"{
+        "asset": {"version": "2.0"},
+        "scene": 0,
+        "scenes": [{"nodes": [0]}],
+        "nodes": [
+            {"translation": [10, 0, 0], "children": [1]},
+            {"scale": [2, 2, 2], "mesh": 0}
+        ],
+        "meshes": [{"primitives": [{"attributes": {"POSITION": 0}, "indices": 1, "material": 0}]}],
+        "materials": [{"name": "Casco", "pbrMetallicRoughness": {"baseColorFactor": [1, 0.5, 0, 1]}}],
+        "buffers": [{"byteLength": 44, "uri": "tri.bin"}],
+        "bufferViews": [
+            {"buffer": 0, "byteOffset": 0, "byteLength": 36},
+            {"buffer": 0, "byteOffset": 36, "byteLength": 6}
+        ],
+        "accessors": [
+            {"bufferView": 0, "componentType": 5126, "count": 3, "type": "VEC3", "min": [0, 0, 0], "max": [1, 1, 0]},
+            {"bufferView": 1, "componentType": 5123, "count": 3, "type": "SCALAR"}
+        ]
+    }"#;
+
+    fn triangle_bin() -> Vec<u8> {
+        let mut bin = Vec::new();
+        for f in [0.0f32, 0.0, 0.0, 1.0, 0.0, 0.0, 0.0, 1.0, 0.0] {
+            bin.extend_from_slice(&f.to_le_bytes());
+        }
+        for i in [0u16, 1, 2] {
+            bin.extend_from_slice(&i.to_le_bytes());
+        }
+        bin.resize(44, 0);
+        bin
+    }
+
+    fn check_triangle(mesh: &ObjMesh) {
+        assert_eq!(mesh.indices, vec![[0, 1, 2]]);
+        assert_eq!(mesh.vertices, vec![Vec3::new(10.0, 0.0, 0.0), Vec3::new(12.0, 0.0, 0.0), Vec3::new(10.0, 2.0, 0.0)]);
+        assert_eq!(mesh.materials.len(), 1);
+        assert_eq!(mesh.materials[0].name, "Casco");
+        assert_eq!(mesh.materials[0].diffuse, Vec3::new(1.0, 0.5, 0.0));
+        assert_eq!(mesh.ranges, vec![MaterialRange { material: Some(0), triangles: 0..1 }]);
+    }
+
+    #[test]
+    fn gltf_applies_node_hierarchy() {
+        let dir = TempDir::new("gltf");
+        dir.write("tri.bin", triangle_bin());
+        let path = dir.write("tri.gltf", JSON);
+        let mesh = load_gltf(&path.to_string_lossy()).unwrap();
+        check_triangle(&mesh);
+        assert_eq!(mesh.dependencies, vec![dir.join("tri.bin")]);
+    }
+
+    #[test]
+    fn glb_reads_embedded_buffer() {
+        let mut json = JSON.replace(r#", "uri": "tri.bin""#, "").into_bytes();
+        while !json.len().is_multiple_of(4) {
+            json.push(b' ');
+        }
+        let bin = triangle_bin();
+        let mut glb = Vec::new();
+        glb.extend_from_slice(b"glTF");
+        glb.extend_from_slice(&2u32.to_le_bytes());
+        glb.extend_from_slice(&((12 + 8 + json.len() + 8 + bin.len()) as u32).to_le_bytes());
+        glb.extend_from_slice(&(json.len() as u32).to_le_bytes());
+        glb.extend_from_slice(b"JSON");
+        glb.extend_from_slice(&json);
+        glb.extend_from_slice(&(bin.len() as u32).to_le_bytes());
+        glb.extend_from_slice(b"BIN\0");
+        glb.extend_from_slice(&bin);
+
+        let dir = TempDir::new("glb");
+        let path = dir.write("tri.glb", glb);
+        let mesh = load_gltf(&path.to_string_lossy()).unwrap();
+        check_triangle(&mesh);
+        assert!(mesh.dependencies.is_empty());
+    }
+}
@@ -0,0 +1,164 @@
+use glam::Vec3;
+
+/// Operador de tone mapping para pasar el buffer HDR a 8 bits.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum ToneMap {
+    /// Solo exposición y recorte (como el framebuffer LDR)
+    Clamp,
+    /// Reinhard extendido con punto blanco `REINHARD_WHITE`
+    Reinhard,
+    /// Aproximación ACES filmic (Narkowicz 2015)
+    Aces,
+}
+
+const REINHARD_WHITE: f32 = 4.0;
+
+impl ToneMap {
+    pub fn next(self) -> Self {
+        match self {
+            ToneMap::Clamp => ToneMap::Reinhard,
+            ToneMap::Reinhard => ToneMap::Aces,
+            ToneMap::Aces => ToneMap::Clamp,
+        }
+    }
+
+    /// Aplica el operador a un color lineal ya multiplicado por la exposición.
+    pub fn apply(self, c: Vec3) -> Vec3 {
+        match self {
+            ToneMap::Clamp => c,
+            ToneMap::Reinhard => {
+                let w2 = REINHARD_WHITE * REINHARD_WHITE;
+                c * (Vec3::ONE + c / w2) / (Vec3::ONE + c)
+            }
+            ToneMap::Aces => {
+                let a = 2.51;
+                let b = 0.03;
+                let cc = 2.43;
+                let d = 0.59;
+                let e = 0.14;
+                (c * (a * c + Vec3::splat(b))) / (c * (cc * c + Vec3::splat(d)) + Vec3::splat(e))
+            }
+        }
+        .clamp(Vec3::ZERO, Vec3::ONE)
+    }
+}
+
+pub fn luminance(c: Vec3) -> f32 {
+    c.dot(Vec3::new(0.2126, 0.7152, 0.0722))
+}
+
+/// Exposición automática: mide la luminancia media (logarítmica) de la imagen
+/// y se adapta hacia `key / media` con una velocidad limitada, como el ojo al
+/// pasar del sol a los planetas exteriores.
+pub struct AutoExposure {
+    pub enabled: bool,
+    /// Exposición actual (multiplicador lineal)
+    pub exposure: f32,
+    /// Gris medio al que se lleva la luminancia media
+    pub key: f32,
+    pub min: f32,
+    pub max: f32,
+    /// Velocidad de adaptación (1/s)
+    pub speed: f32,
+}
+
+impl Default for AutoExposure {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl AutoExposure {
+    pub fn new() -> Self {
+        Self {
+            enabled: true,
+            exposure: 1.0,
+            key: 0.35,
+            min: 0.25,
+            max: 2.5,
+            speed: 1.5,
+        }
+    }
+
+    /// Actualiza la exposición a partir del buffer HDR. Solo cuenta los pixeles
+    /// con algo de luz: el fondo negro del espacio dispararía la exposición.
+    pub fn update(&mut self, hdr: &[Vec3], dt: f32) {
+        if !self.enabled {
+            return;
+        }
+        let mut log_sum = 0.0;
+        let mut count = 0u32;
+        // Submuestreo: con uno de cada 16 pixeles sobra para una media
+        for c in hdr.iter().step_by(16) {
+            let l = luminance(*c);
+            if l > 0.02 {
+                log_sum += l.ln();
+                count += 1;
+            }
+        }
+        let target = if count == 0 {
+            1.0
+        } else {
+            let avg = (log_sum / count as f32).exp();
+            (self.key / avg).clamp(self.min, self.max)
+        };
+        let k = 1.0 - (-dt * self.speed).exp();
+        self.exposure += (target - self.exposure) * k;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn tone_maps_are_monotonic_and_clamped() {
+        for tone_map in [ToneMap::Clamp, ToneMap::Reinhard, ToneMap::Aces] {
+            let mapped: Vec<f32> = (0..=200).map(|i| tone_map.apply(Vec3::splat(i as f32 * 0.1)).x).collect();
+            assert!(mapped.windows(2).all(|p| p[0] <= p[1]), "{:?}", tone_map);
+            assert!(mapped.iter().all(|m| (0.0..=1.0).contains(m)), "{:?}", tone_map);
+            assert_eq!(mapped[0], 0.0);
+        }
+        // El punto blanco de Reinhard llega justo a 1; por debajo no
+        assert!((ToneMap::Reinhard.apply(Vec3::splat(REINHARD_WHITE)).x - 1.0).abs() < 1e-6);
+        assert!(ToneMap::Reinhard.apply(Vec3::splat(REINHARD_WHITE * 0.5)).x < 0.9);
+    }
+
+    /// Exposición tras `steps` actualizaciones de `dt` sobre `hdr`.
+    fn adapt(hdr: &[Vec3], steps: usize, dt: f32) -> f32 {
+        let mut auto = AutoExposure::default();
+        for _ in 0..steps {
+            auto.update(hdr, dt);
+        }
+        auto.exposure
+    }
+
+    #[test]
+    fn auto_exposure_converges_within_limits() {
+        let auto = AutoExposure::default();
+        // Gris uniforme: la luminancia es el propio valor
+        let gray = vec![Vec3::splat(0.2); 256];
+        assert!((adapt(&gray, 200, 0.1) - auto.key / 0.2).abs() < 1e-3);
+        // Muy brillante u oscuro: se queda en los límites
+        assert!((adapt(&vec![Vec3::splat(50.0); 256], 200, 0.1) - auto.min).abs() < 1e-3);
+        assert!((adapt(&vec![Vec3::splat(0.03); 256], 200, 0.1) - auto.max).abs() < 1e-3);
+
+        // Velocidad limitada: un frame solo recorre parte del camino
+        let k = 1.0 - (-0.1 * auto.speed).exp();
+        let one = adapt(&gray, 1, 0.1);
+        assert!((one - (1.0 + (auto.key / 0.2 - 1.0) * k)).abs() < 1e-5, "{}", one);
+    }
+
+    #[test]
+    fn auto_exposure_ignores_black_pixels() {
+        let gray = vec![Vec3::splat(0.2); 256];
+        let mut mixed = gray.clone();
+        // La mitad de los pixeles medidos (uno de cada 16) son espacio negro
+        for c in mixed.iter_mut().step_by(32) {
+            *c = Vec3::ZERO;
+        }
+        assert_eq!(adapt(&mixed, 50, 0.1), adapt(&gray, 50, 0.1));
+        // Todo negro: se vuelve a la exposición neutra
+        assert!((adapt(&vec![Vec3::ZERO; 256], 200, 0.1) - 1.0).abs() < 1e-3);
+    }
+}
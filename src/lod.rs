@@ -0,0 +1,141 @@
+use crate::object::ObjMesh;
+
+/// Margen relativo alrededor de cada umbral: para subir de nivel hay que
+/// superarlo en un 15 % y para bajar quedarse un 15 % por debajo, así un
+/// cuerpo justo en el límite no alterna de malla en cada frame.
+const HYSTERESIS: f32 = 0.15;
+
+pub struct LodLevel {
+    pub mesh: ObjMesh,
+    /// Radio mínimo en pantalla (pixeles) para usar este nivel
+    pub min_radius_px: f32,
+}
+
+/// Varias resoluciones de la misma malla, de la más simple a la más detallada.
+#[derive(Default)]
+pub struct LodSet {
+    pub levels: Vec<LodLevel>,
+}
+
+impl LodSet {
+    pub fn new() -> Self {
+        Self { levels: Vec::new() }
+    }
+
+    /// Añade un nivel; deben añadirse en orden creciente de detalle.
+    pub fn push(&mut self, mesh: ObjMesh, min_radius_px: f32) {
+        self.levels.push(LodLevel { mesh, min_radius_px });
+    }
+
+    /// Esferas a partir de `finest`, el nivel más detallado: los demás son
+    /// `finest` simplificado a 5120, 1280, 320 y 80 triángulos, cada uno a
+    /// partir del anterior. Al salir todos de la misma malla el cuerpo no
+    /// cambia de tamaño ni de costuras al cambiar de nivel.
+    ///
+    /// `finest` se suelda antes: un OBJ con normales por cara repite cada
+    /// vértice en cada esquina (`sphere.obj` pasaría de 25k a 146k vértices)
+    /// y la simplificación necesita vértices compartidos.
+    pub fn spheres(mut finest: ObjMesh) -> Self {
+        if finest.weld(1e-5) > 0 && !finest.normals.is_empty() {
+            finest.compute_normals();
+        }
+        let mut coarser = Vec::new();
+        let mut mesh = finest.clone();
+        for target in [5120, 1280, 320, 80] {
+            mesh.simplify(target);
+            coarser.push(mesh.clone());
+        }
+        let mut set = Self::new();
+        for (mesh, min_radius_px) in coarser.into_iter().rev().zip([0.0, 8.0, 24.0, 70.0]) {
+            set.push(mesh, min_radius_px);
+        }
+        set.push(finest, 200.0);
+        set
+    }
+
+    /// Radio de la esfera envolvente (igual en todos los niveles de `spheres`).
+    pub fn radius(&self) -> f32 {
+        self.levels.last().map_or(0.0, |l| l.mesh.bounds.radius)
+    }
+
+    /// Elige el nivel para un cuerpo con radio `radius_px` en pantalla.
+    /// `current` es el nivel que usó el cuerpo en el frame anterior y se
+    /// actualiza con el nuevo. `None` si el conjunto no tiene niveles.
+    pub fn select(&self, current: &mut usize, radius_px: f32) -> Option<&ObjMesh> {
+        let last = self.levels.len().checked_sub(1)?;
+        let mut level = (*current).min(last);
+        while level + 1 < self.levels.len() && radius_px > self.levels[level + 1].min_radius_px * (1.0 + HYSTERESIS) {
+            level += 1;
+        }
+        while level > 0 && radius_px < self.levels[level].min_radius_px * (1.0 - HYSTERESIS) {
+            level -= 1;
+        }
+        *current = level;
+        Some(&self.levels[level].mesh)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Niveles sin malla que distinguir, solo para probar la selección.
+    fn thresholds(min_radius_px: &[f32]) -> LodSet {
+        let mut set = LodSet::new();
+        for &r in min_radius_px {
+            set.push(ObjMesh::new(Vec::new(), Vec::new()), r);
+        }
+        set
+    }
+
+    #[test]
+    fn select_waits_for_the_hysteresis_band() {
+        let set = thresholds(&[0.0, 10.0, 100.0]);
+        let mut level = 0;
+        set.select(&mut level, 11.0);
+        assert_eq!(level, 0, "10 % por encima del umbral aún no basta");
+        set.select(&mut level, 12.0);
+        assert_eq!(level, 1);
+
+        // Oscilando alrededor del umbral no se cambia de nivel
+        for radius in [9.0, 11.0, 8.6, 11.4, 10.0] {
+            set.select(&mut level, radius);
+            assert_eq!(level, 1, "radio {}", radius);
+        }
+        set.select(&mut level, 8.0);
+        assert_eq!(level, 0);
+
+        // Un salto grande cruza varios niveles de una vez
+        set.select(&mut level, 1000.0);
+        assert_eq!(level, 2);
+    }
+
+    #[test]
+    fn select_on_empty_set_has_no_mesh() {
+        let mut level = 3;
+        assert!(LodSet::default().select(&mut level, 50.0).is_none());
+        assert!(thresholds(&[0.0]).select(&mut level, 50.0).is_some());
+        assert_eq!(level, 0);
+    }
+
+    #[test]
+    fn bundled_sphere_shares_its_vertices() {
+        let mesh = ObjMesh::load("assets/models/sphere.obj").unwrap();
+        let triangles = mesh.indices.len();
+        let set = LodSet::spheres(mesh);
+        let finest = &set.levels.last().unwrap().mesh;
+        // 24578 `v` en el archivo; unos pocos de más por las costuras, no uno por esquina
+        assert!(finest.vertices.len() <= 25_000, "{} vértices", finest.vertices.len());
+        assert_eq!(finest.indices.len(), triangles);
+        assert_eq!(finest.normals.len(), finest.vertices.len());
+
+        // Los demás niveles son la misma esfera con menos triángulos
+        let counts: Vec<usize> = set.levels.iter().map(|l| l.mesh.indices.len()).collect();
+        for (count, target) in counts.iter().zip([80, 320, 1280, 5120]) {
+            assert!(*count <= target && *count > target * 3 / 4, "{:?}", counts);
+        }
+        for level in &set.levels[..4] {
+            assert!(level.mesh.vertices.iter().all(|v| (v.length() / set.radius() - 1.0).abs() < 0.1));
+        }
+    }
+}
@@ -2,18 +2,30 @@ mod renderer;
 mod camera;
 mod planet;
 mod object;
+mod material;
+mod gltf_import;
+mod mesh_cache;
+mod mesh_tools;
+mod assets;
+mod catalog;
 mod skybox;
+mod starfield;
 mod warp;
 mod movement;
 mod texture;
 mod utils;
 mod math;
+mod generator;
+mod hdr;
+mod postfx;
+mod aa;
+mod lod;
 
 use raylib::prelude::*;
-use renderer::{SoftwareRenderer, PlanetShaderKind};
+use renderer::{SoftwareRenderer, PlanetShaderKind, ShaderParams, CloudLayer};
 use camera::Camera;
 use skybox::Skybox;
-use planet::Planet;
+use planet::{Moon, Planet, Ring};
 use movement::update_third_person;
 use warp::Warp;
 use utils::*;
@@ -36,7 +48,81 @@ fn keep_camera_outside_planets(cam: &mut Camera, ship_pos: &mut glam::Vec3, plan
     }
 }
 
+/// Sistema hecho a mano que se usa cuando no se pasa semilla.
+fn default_system() -> Vec<Planet> {
+    let mut planets = vec![
+        Planet::with_shader("Sol", 0.0, 0.0, 0.0, 4.0, Color::YELLOW, PlanetShaderKind::Sun),
+        // Increased orbit radii to give more spacing between planets
+        Planet::with_shader("Tierra", 18.0, 0.7, 1.5, 1.3, Color::BLUE, PlanetShaderKind::Earth),
+        Planet::with_shader("Volcanico", 28.0, 0.5, 1.1, 1.5, Color::RED, PlanetShaderKind::Volcanic),
+        Planet::with_shader("Helado", 40.0, 0.42, 0.9, 1.6, Color::SKYBLUE, PlanetShaderKind::Ice),
+        Planet::with_shader("Gaseoso", 55.0, 0.35, 0.7, 2.7, Color::BEIGE, PlanetShaderKind::Gas),
+        // SuperTierra: una super tierra con anillos rojizos (usa shader Earth con color amarilloso)
+        Planet::with_shader("SuperTierra", 72.0, 0.28, 0.9, 2.0, Color::new(200, 180, 80, 255), PlanetShaderKind::SuperEarth),
+    ];
+
+    // Nubes: la Tierra más nubosa, la SuperTierra con nubes lentas y escasas
+    planets[1].params.clouds = Some(CloudLayer::new(0.45, 0.12));
+    let mut super_clouds = CloudLayer::new(0.3, 0.05);
+    super_clouds.seed = 17.0;
+    planets[5].params.clouds = Some(super_clouds);
+
+    // Luna que orbita la Tierra
+    planets[1].moons.push(Moon::new(3.0, 1.0, 0.5, Color::LIGHTGRAY));
+
+    let gas_scale = planets[4].scale;
+    planets[4].ring = Some(Ring { inner: gas_scale * 1.6, outer: gas_scale * 3.0, color: Color::new(200, 180, 140, 200) });
+    // SuperEarth has reddish/lava-like rings
+    let super_scale = planets[5].scale;
+    planets[5].ring = Some(Ring { inner: super_scale * 1.25, outer: super_scale * 2.5, color: Color::new(180, 60, 30, 200) });
+
+    planets
+}
+
+/// Semilla del cielo procedural cuando no hay imágenes de skybox
+const SKY_SEED: u64 = 2024;
+
+/// Cielo con las estrellas del catálogo incluido (y sus constelaciones si
+/// `lines`). Los CSV quedan vigilados para la recarga en caliente.
+fn load_real_sky(assets: &mut assets::Assets, lines: bool) -> Result<Skybox, catalog::CatalogError> {
+    let stars = assets.watch(catalog::STARS_FILE);
+    let mut cat = catalog::StarCatalog::load(&stars.to_string_lossy())?;
+    if lines {
+        let path = assets.watch(catalog::LINES_FILE);
+        cat.load_lines(&path.to_string_lossy())?;
+    }
+    for warning in &cat.warnings {
+        eprintln!("aviso: {}", warning);
+    }
+    Ok(Skybox::catalog(&cat, lines))
+}
+
+/// Arreglos de la nave tras cargarla (o recargarla).
+fn prepare_ship(ship: &mut object::ObjMesh) {
+    // Limpia los vértices duplicados del exportador y ajusta la esfera
+    // envolvente a la forma alargada de la nave (mejor descarte por frustum)
+    ship.weld(1e-5);
+    ship.compute_bounds();
+    // La nave no es una malla cerrada: dibujar ambas caras
+    ship.cull = object::CullMode::None;
+}
+
 fn main() {
+    // Carpeta de assets: SPACETRAVEL_ASSETS, ./assets o junto al ejecutable
+    let mut assets = assets::Assets::locate();
+
+    // `cargo run -- --bake-meshes` genera los cachés binarios de todos los modelos y sale
+    if std::env::args().nth(1).as_deref() == Some("--bake-meshes") {
+        match mesh_cache::bake_dir(&assets.path("models")) {
+            Ok(n) => eprintln!("{} modelos en caché", n),
+            Err(e) => {
+                eprintln!("{}", e);
+                std::process::exit(1);
+            }
+        }
+        return;
+    }
+
     let (mut rl, thread) = raylib::init()
         .size(1280, 720)
         .title("SpaceTravel - Proyecto Final")
@@ -57,29 +143,57 @@ fn main() {
         v3(0.0, 0.0, 30.0),
     );
 
-    // Skybox
-    let sky = Skybox::load();
+    // Skybox: con `--real-sky` las estrellas del catálogo (`--constellations`
+    // añade sus líneas); si no, panorama o caras de assets/skybox y, sin
+    // imágenes, un cielo procedural
+    let has_flag = |flag: &str| std::env::args().any(|a| a == flag);
+    let constellations = has_flag("--constellations");
+    let real_sky = constellations || has_flag("--real-sky");
+    let real = if real_sky {
+        load_real_sky(&mut assets, constellations).map_err(|e| eprintln!("aviso: {}; usando el skybox de imágenes", e)).ok()
+    } else {
+        None
+    };
+    let mut sky = real.unwrap_or_else(|| {
+        Skybox::load(&mut assets).unwrap_or_else(|e| {
+            eprintln!("{}; usando un cielo procedural", e);
+            Skybox::procedural(&starfield::StarField::new(SKY_SEED))
+        })
+    });
 
     // Modelos
-    let sphere = object::ObjMesh::load("assets/models/sphere.obj");
-    let ship = object::ObjMesh::load("assets/models/ship.obj");
+    // Esferas con varios niveles de detalle; sphere.obj es el más fino. Si no
+    // se puede cargar se genera una esfera UV del mismo radio.
+    let sphere_mesh = assets.mesh_or("models/sphere.obj", || object::ObjMesh::uv_sphere(128, 64, 1.28));
+    let mut spheres = lod::LodSet::spheres(assets.get_mesh(sphere_mesh).clone());
+    // La nave puede ser un glTF (.glb / .gltf) o un OBJ: se usa el primero que
+    // exista; sin archivo, un cubo
+    let ship_file = ["models/ship.glb", "models/ship.gltf", "models/ship.obj"]
+        .into_iter()
+        .find(|rel| assets.path(rel).is_file())
+        .unwrap_or("models/ship.obj");
+    let ship = assets.mesh_or(ship_file, || object::ObjMesh::cube(1.0));
+    prepare_ship(assets.get_mesh_mut(ship));
 
     // Warp system
     let mut warp = Warp::new();
 
-    // Planetas con escala / color / shader
-    let mut planets: Vec<Planet> = vec![
-        Planet::with_shader("Sol", 0.0, 0.0, 0.0, 4.0, Color::YELLOW, PlanetShaderKind::Sun),
-        // Increased orbit radii to give more spacing between planets
-        Planet::with_shader("Tierra", 18.0, 0.7, 1.5, 1.3, Color::BLUE, PlanetShaderKind::Earth),
-        Planet::with_shader("Volcanico", 28.0, 0.5, 1.1, 1.5, Color::RED, PlanetShaderKind::Volcanic),
-        Planet::with_shader("Helado", 40.0, 0.42, 0.9, 1.6, Color::SKYBLUE, PlanetShaderKind::Ice),
-        Planet::with_shader("Gaseoso", 55.0, 0.35, 0.7, 2.7, Color::BEIGE, PlanetShaderKind::Gas),
-        // SuperTierra: una super tierra con anillos rojizos (usa shader Earth con color amarilloso)
-        Planet::with_shader("SuperTierra", 72.0, 0.28, 0.9, 2.0, Color::new(200, 180, 80, 255), PlanetShaderKind::SuperEarth),
-    ];
-
-    let earth_idx = 1;
+    // Planetas con escala / color / shader.
+    // `cargo run -- <semilla>` genera un sistema aleatorio reproducible.
+    let seed = std::env::args().skip(1).find_map(|a| a.parse::<u64>().ok());
+    let mut planets: Vec<Planet> = if let Some(seed) = seed {
+        eprintln!("Generating system from seed {}", seed);
+        let planets = generator::generate_system(seed);
+        for p in &planets {
+            eprintln!(
+                "  {}: orbit={:.1} scale={:.2} ring={} moons={}",
+                p.name, p.orbit_radius, p.scale, p.ring.is_some(), p.moons.len()
+            );
+        }
+        planets
+    } else {
+        default_system()
+    };
 
     let light_dir = Vec3::new(1.0, -0.4, -0.2);
 
@@ -103,9 +217,64 @@ fn main() {
         }
         let dt = rl.get_frame_time();
 
+        // Recarga en caliente de lo que haya cambiado en disco
+        let changes = assets.poll();
+        if changes.meshes.contains(&sphere_mesh) {
+            spheres = lod::LodSet::spheres(assets.get_mesh(sphere_mesh).clone());
+        }
+        if changes.meshes.contains(&ship) {
+            prepare_ship(assets.get_mesh_mut(ship));
+        }
+        if real_sky && !changes.files.is_empty() {
+            match load_real_sky(&mut assets, constellations) {
+                Ok(s) => sky = s,
+                Err(e) => eprintln!("aviso: {}", e),
+            }
+        }
+        if !real_sky && changes.textures.iter().any(|&t| assets.texture_path(t).starts_with(Skybox::DIR)) {
+            match Skybox::load(&mut assets) {
+                Ok(s) => sky = s,
+                Err(e) => eprintln!("aviso: {}", e),
+            }
+        }
+
         // Actualizar controles (mueve la nave y actualiza la cámara en 3ª persona)
         update_third_person(&rl, &mut cam, &mut ship_pos, dt, warp.is_active());
 
+        // H: activar/desactivar HDR, T: cambiar operador de tone mapping
+        if rl.is_key_pressed(KeyboardKey::KEY_H) {
+            let on = !renderer.hdr_enabled();
+            renderer.set_hdr(on);
+            eprintln!("HDR {}", if on { "on" } else { "off" });
+        }
+        if rl.is_key_pressed(KeyboardKey::KEY_T) {
+            renderer.tone_map = renderer.tone_map.next();
+            eprintln!("Tone map: {:?}", renderer.tone_map);
+        }
+
+        // F1–F4: activar/desactivar cada efecto de post-proceso
+        for (i, key) in [
+            KeyboardKey::KEY_F1,
+            KeyboardKey::KEY_F2,
+            KeyboardKey::KEY_F3,
+            KeyboardKey::KEY_F4,
+        ]
+        .iter()
+        .enumerate()
+        {
+            if rl.is_key_pressed(*key) {
+                if let Some(on) = renderer.post.toggle(i) {
+                    eprintln!("{}: {}", renderer.post.passes[i].effect.name(), if on { "on" } else { "off" });
+                }
+            }
+        }
+
+        // F5: cambiar modo de anti-aliasing
+        if rl.is_key_pressed(KeyboardKey::KEY_F5) {
+            renderer.aa = renderer.aa.next();
+            eprintln!("Anti-aliasing: {:?}", renderer.aa);
+        }
+
         // Warp 1–5
         for (i, key) in [
             KeyboardKey::KEY_ONE,
@@ -181,66 +350,77 @@ fn main() {
             renderer.draw_orbit(p.orbit_radius, &cam, Color::LIGHTGRAY);
         }
 
-        // Dibujar planetas con shaders bonitos
-        for p in &planets {
+        // Dibujar planetas con shaders bonitos (malla elegida por tamaño en pantalla)
+        for p in planets.iter_mut() {
             let pos = p.position();
-            renderer.draw_mesh_shaded(
-                &sphere,
-                pos,
-                p.scale,
-                p.color,
-                &cam,
-                p.shader,
-                light_dir,
-            );
-            // Dibujar anillos solo para planetas específicos
-            match p.shader {
-                crate::renderer::PlanetShaderKind::Gas => {
-                    renderer.draw_ring(pos, p.scale * 1.6, p.scale * 3.0, &cam, Color::new(200, 180, 140, 200));
-                }
-                crate::renderer::PlanetShaderKind::SuperEarth => {
-                    // SuperEarth has reddish/lava-like rings
-                    renderer.draw_ring(pos, p.scale * 1.25, p.scale * 2.5, &cam, Color::new(180, 60, 30, 200));
+            let radius_px = renderer.screen_radius(pos, p.scale * spheres.radius(), &cam);
+            if let Some(sphere) = spheres.select(&mut p.lod, radius_px) {
+                renderer.draw_mesh_shaded(
+                    sphere,
+                    pos,
+                    p.scale,
+                    p.color,
+                    &cam,
+                    p.shader,
+                    p.params,
+                    light_dir,
+                );
+            }
+            if let Some(ring) = p.ring {
+                renderer.draw_ring(pos, ring.inner, ring.outer, &cam, ring.color, light_dir);
+            }
+            for m in p.moons.iter_mut() {
+                let moon_pos = m.position(pos);
+                let radius_px = renderer.screen_radius(moon_pos, m.scale * spheres.radius(), &cam);
+                if let Some(sphere) = spheres.select(&mut m.lod, radius_px) {
+                    renderer.draw_mesh_shaded(
+                        sphere,
+                        moon_pos,
+                        m.scale,
+                        m.color,
+                        &cam,
+                        m.shader,
+                        ShaderParams::default(),
+                        light_dir,
+                    );
                 }
-                _ => {}
             }
         }
         eprintln!("frame {}: after planets", frame_count);
 
-        // (Temporal) Desactivado: halo solar para depuración del crash.
-        // if let Some(sun) = planets.get(0) {
-        //     renderer.draw_sun_glow(sun.position(), sun.scale, &cam);
-        // }
-
-        // Luna que orbita la Tierra
-        let earth_pos = planets[earth_idx].position();
-        let moon_angle = rl.get_time() as f32;
-        let moon_pos = earth_pos + v3(3.0 * moon_angle.cos(), 0.0, 3.0 * moon_angle.sin());
-
-        renderer.draw_mesh_shaded(
-            &sphere,
-            moon_pos,
-            0.5,
-            Color::LIGHTGRAY,
-            &cam,
-            PlanetShaderKind::Ice,
-            light_dir,
-        );
-        eprintln!("frame {}: after moon", frame_count);
-
         // Dibujar la nave en `ship_pos`. Rotamos 180deg para corregir orientación del modelo.
         renderer.draw_mesh_shaded_rot(
-            &ship,
+            assets.get_mesh(ship),
             ship_pos,
             0.9,
             std::f32::consts::PI,
             Color::WHITE,
             &cam,
             PlanetShaderKind::Default,
+            ShaderParams::default(),
             light_dir,
         );
         eprintln!("frame {}: after ship", frame_count);
 
+        // Anillos y demás superficies semitransparentes, de atrás hacia
+        // delante sobre todo lo opaco (incluida la nave)
+        renderer.resolve_translucent();
+
+        // Corona solar y lens flare (aditivos). Se atenúan según cuánto del
+        // sol queda tapado en el z-buffer, por eso van después de la nave.
+        if let Some(sun) = planets.get(0) {
+            let vis = renderer.sun_visibility(sun.position(), sun.scale, &cam);
+            renderer.draw_sun_glow(sun.position(), sun.scale, &cam, vis);
+            renderer.draw_lens_flare(sun.position(), sun.scale, &cam, vis);
+        }
+
+        // Post-proceso (bloom, aberración...) y luego exposición automática y
+        // tone mapping, tras los que van la viñeta y el grano (sin HDR todo
+        // se aplica en `post_process`)
+        renderer.post_process();
+        renderer.resolve_hdr(dt);
+        renderer.apply_aa();
+
         // Presentar framebuffer a pantalla completa
         {
             eprintln!("frame {}: before begin_drawing", frame_count);
@@ -267,25 +447,12 @@ fn main() {
                 }
             }
 
-            // Sun fill + glow (additive) - keep after planet rasterization so it blends
-            if let Some(sun) = planets.get(0) {
-                // draw the halo/glow (keep only glow for now to avoid recent crash)
-                renderer.draw_sun_glow(sun.position(), sun.scale, &cam);
-            }
-
-            // Draw ship (rotated 180deg)
-            renderer.draw_mesh_shaded_rot(
-                &ship,
-                ship_pos,
-                0.9,
-                std::f32::consts::PI,
-                Color::WHITE,
-                &cam,
-                PlanetShaderKind::Default,
-                light_dir,
+            d.draw_text("WASD para mover | SPACE/CTRL subir/bajar | 1–5 Warp | H HDR | T tone map | F1–F4 post | F5 AA", 10, 10, 20, Color::WHITE);
+            let stats = renderer.stats;
+            d.draw_text(
+                &format!("Objetos: {} dibujados, {} descartados | {} triángulos", stats.drawn, stats.culled, stats.triangles),
+                10, 34, 20, Color::WHITE,
             );
-
-            d.draw_text("WASD para mover | SPACE/CTRL subir/bajar | 1–5 Warp", 10, 10, 20, Color::WHITE);
         }
     }
 }
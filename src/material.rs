@@ -0,0 +1,133 @@
+use glam::Vec3;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::object::{parse_floats, ObjError, ObjErrorKind};
+use crate::texture::{Filter, TextureCPU};
+
+/// Material de un `.mtl` (modelo de Phong de Wavefront).
+#[derive(Clone)]
+pub struct Material {
+    pub name: String,
+    /// Color difuso (`Kd`)
+    pub diffuse: Vec3,
+    /// Color especular (`Ks`)
+    pub specular: Vec3,
+    /// Exponente especular (`Ns`)
+    pub shininess: f32,
+    /// Emisión (`Ke`); también alimenta el bloom
+    pub emissive: Vec3,
+    /// Opacidad (`d`, o `1 - Tr`)
+    pub opacity: f32,
+    /// Textura difusa (`map_Kd`), multiplica a `diffuse`; su alfa, a `opacity`
+    pub diffuse_map: Option<TextureCPU>,
+    /// Archivo de `map_Kd`, aunque no se haya podido leer
+    pub diffuse_file: Option<PathBuf>,
+}
+
+impl Material {
+    /// Valores por defecto de la especificación: gris difuso, sin brillo ni emisión, opaco.
+    pub fn new(name: &str) -> Self {
+        Self {
+            name: name.to_string(),
+            diffuse: Vec3::splat(0.8),
+            specular: Vec3::ZERO,
+            shininess: 0.0,
+            emissive: Vec3::ZERO,
+            opacity: 1.0,
+            diffuse_map: None,
+            diffuse_file: None,
+        }
+    }
+}
+
+/// Lee todos los materiales de un `.mtl`. Las texturas se buscan junto al archivo.
+pub fn load_mtl(path: &str) -> Result<Vec<Material>, ObjError> {
+    let text = fs::read_to_string(path).map_err(|source| ObjError::Io { path: path.to_string(), source })?;
+    parse_mtl(&text, path)
+}
+
+/// Como `load_mtl`, pero sobre el texto ya leído.
+pub fn parse_mtl(text: &str, path: &str) -> Result<Vec<Material>, ObjError> {
+    let dir = Path::new(path).parent().unwrap_or(Path::new(""));
+    let mut materials: Vec<Material> = Vec::new();
+
+    for (n, raw) in text.lines().enumerate() {
+        let err = |kind| ObjError::Parse { path: path.to_string(), line: n + 1, kind };
+        let line = raw.split('#').next().unwrap_or("");
+        let mut parts = line.split_whitespace();
+        let Some(keyword) = parts.next() else { continue };
+
+        if keyword == "newmtl" {
+            let name = parts.next().ok_or_else(|| err(ObjErrorKind::MissingValues { keyword: keyword.to_string(), expected: 1 }))?;
+            materials.push(Material::new(name));
+            continue;
+        }
+        // Propiedades antes del primer `newmtl`: no hay material al que asignarlas
+        let Some(m) = materials.last_mut() else { continue };
+        match keyword {
+            "Kd" | "Ks" | "Ke" => {
+                let [r, g, b] = parse_floats(keyword, &mut parts, 3).map_err(err)?;
+                let c = Vec3::new(r, g, b);
+                match keyword {
+                    "Kd" => m.diffuse = c,
+                    "Ks" => m.specular = c,
+                    _ => m.emissive = c,
+                }
+            }
+            "Ns" => m.shininess = parse_floats(keyword, &mut parts, 1).map_err(err)?[0],
+            "d" => m.opacity = parse_floats(keyword, &mut parts, 1).map_err(err)?[0].clamp(0.0, 1.0),
+            "Tr" => m.opacity = 1.0 - parse_floats(keyword, &mut parts, 1).map_err(err)?[0].clamp(0.0, 1.0),
+            "map_Kd" => {
+                // Las opciones (-s, -o, ...) van antes; el archivo es lo último
+                let file = parts.last().ok_or_else(|| err(ObjErrorKind::MissingValues { keyword: keyword.to_string(), expected: 1 }))?;
+                let tex_path = dir.join(file);
+                m.diffuse_file = Some(tex_path.clone());
+                // Una textura que falta o no se puede leer deja el material sin ella
+                if !tex_path.exists() {
+                    eprintln!("{}:{}: textura {} no encontrada", path, n + 1, tex_path.display());
+                } else {
+                    match TextureCPU::load(&tex_path.to_string_lossy()) {
+                        Ok(mut tex) => {
+                            // Con mips: el renderer elige nivel según la distancia
+                            tex.filter = Filter::Trilinear;
+                            m.diffuse_map = Some(tex);
+                        }
+                        Err(e) => eprintln!("{}:{}: aviso: {}", path, n + 1, e),
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+    Ok(materials)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn mtl_reads_phong_properties() {
+        let text = "# cabecera\nnewmtl Casco\nKd 0.5 0.25 1\nKs 1 1 1\nNs 64\nKe 0 0 0.5\nd 0.4\n\
+                    newmtl Motor\nTr 0.25\nmap_Kd -s 1 1 1 no_existe.png\n";
+        let mats = parse_mtl(text, "nave.mtl").unwrap();
+        assert_eq!(mats.len(), 2);
+        assert_eq!(mats[0].name, "Casco");
+        assert_eq!(mats[0].diffuse, Vec3::new(0.5, 0.25, 1.0));
+        assert_eq!(mats[0].specular, Vec3::ONE);
+        assert_eq!(mats[0].shininess, 64.0);
+        assert_eq!(mats[0].emissive, Vec3::new(0.0, 0.0, 0.5));
+        assert_eq!(mats[0].opacity, 0.4);
+        assert_eq!(mats[1].opacity, 0.75);
+        assert!(mats[1].diffuse_map.is_none());
+    }
+
+    #[test]
+    fn mtl_errors_report_line() {
+        match parse_mtl("newmtl A\nKd 1 0\n", "malo.mtl") {
+            Err(ObjError::Parse { line, .. }) => assert_eq!(line, 2),
+            _ => panic!("esperaba error de formato"),
+        }
+    }
+}
@@ -0,0 +1,504 @@
+use glam::{Vec2, Vec3};
+use std::error::Error;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::UNIX_EPOCH;
+
+use crate::material::Material;
+use crate::object::{load_model, MaterialRange, ObjMesh};
+use crate::texture::{Filter, Format, Texels, TextureCPU, Wrap};
+
+/// Cabecera del formato: "SpaceTravel Mesh Cache"
+const MAGIC: &[u8; 4] = b"STMC";
+/// Subir al cambiar el formato (o lo que guarda): los caches viejos se ignoran
+const VERSION: u32 = 2;
+const EXTENSION: &str = "meshcache";
+
+/// Caché binario junto al modelo: `ship.obj` -> `ship.obj.meshcache`.
+pub fn cache_path(path: &str) -> PathBuf {
+    let mut p = PathBuf::from(path).into_os_string();
+    p.push(".");
+    p.push(EXTENSION);
+    PathBuf::from(p)
+}
+
+/// Carga un modelo a través de su caché binario. Si el caché no existe o no
+/// corresponde a la fuente (por fecha y tamaño, o si no por hash del
+/// contenido), se carga la fuente con `load_model` y se reescribe el caché.
+/// Los materiales se guardan con sus texturas; los `.mtl`, texturas y `.bin`
+/// de los que salen quedan anotados con su fecha y tamaño (o como ausentes),
+/// y si alguno cambia, o aparece uno que faltaba, el caché se descarta.
+pub fn load_cached(path: &str) -> Result<ObjMesh, Box<dyn Error>> {
+    let cache = cache_path(path);
+    if let Some(mesh) = read_cache(&cache, Path::new(path)) {
+        return Ok(mesh);
+    }
+    let mesh = load_model(path)?;
+    if let Err(e) = write_cache(&cache, Path::new(path), &mesh) {
+        eprintln!("aviso: no se pudo escribir {}: {}", cache.display(), e);
+    }
+    Ok(mesh)
+}
+
+/// Genera (o actualiza) el caché de cada modelo bajo `dir`, recursivamente.
+/// Devuelve cuántos modelos se procesaron.
+pub fn bake_dir(dir: &Path) -> Result<usize, Box<dyn Error>> {
+    let mut baked = 0;
+    for entry in fs::read_dir(dir)? {
+        let path = entry?.path();
+        if path.is_dir() {
+            baked += bake_dir(&path)?;
+            continue;
+        }
+        let ext = path.extension().and_then(|e| e.to_str()).unwrap_or("").to_ascii_lowercase();
+        if !matches!(ext.as_str(), "obj" | "gltf" | "glb") {
+            continue;
+        }
+        let name = path.to_string_lossy();
+        let mesh = load_cached(&name).map_err(|e| format!("{}: {}", name, e))?;
+        eprintln!("  {}: {} vértices, {} triángulos", name, mesh.vertices.len(), mesh.indices.len());
+        baked += 1;
+    }
+    Ok(baked)
+}
+
+/// Identidad de la fuente guardada en la cabecera.
+#[derive(PartialEq)]
+struct SourceStamp {
+    len: u64,
+    mtime_secs: u64,
+    mtime_nanos: u32,
+}
+
+fn stamp(path: &Path) -> Option<SourceStamp> {
+    let meta = fs::metadata(path).ok()?;
+    let mtime = meta.modified().ok()?.duration_since(UNIX_EPOCH).ok()?;
+    Some(SourceStamp { len: meta.len(), mtime_secs: mtime.as_secs(), mtime_nanos: mtime.subsec_nanos() })
+}
+
+/// FNV-1a de 64 bits: no es criptográfico, solo detecta cambios.
+fn hash_bytes(bytes: &[u8]) -> u64 {
+    bytes.iter().fold(0xcbf2_9ce4_8422_2325, |h, &b| (h ^ b as u64).wrapping_mul(0x0000_0100_0000_01b3))
+}
+
+/// Posición de la fecha de la fuente en el archivo (tras `MAGIC` y `VERSION`)
+const STAMP_OFFSET: usize = 8;
+
+/// Las dependencias se guardan relativas a la carpeta del modelo, así el
+/// caché sigue valiendo aunque la raíz de assets se escriba de otra forma.
+fn relative_to(dep: &Path, dir: &Path) -> String {
+    dep.strip_prefix(dir).unwrap_or(dep).to_string_lossy().into_owned()
+}
+
+/// Escritor secuencial en little endian; el inverso de `Reader`.
+struct Writer {
+    out: Vec<u8>,
+}
+
+impl Writer {
+    fn u8(&mut self, v: u8) {
+        self.out.push(v);
+    }
+
+    fn u32(&mut self, v: u32) {
+        self.out.extend_from_slice(&v.to_le_bytes());
+    }
+
+    fn u64(&mut self, v: u64) {
+        self.out.extend_from_slice(&v.to_le_bytes());
+    }
+
+    fn f32(&mut self, v: f32) {
+        self.out.extend_from_slice(&v.to_le_bytes());
+    }
+
+    fn vec3(&mut self, v: Vec3) {
+        for f in v.to_array() {
+            self.f32(f);
+        }
+    }
+
+    fn str(&mut self, s: &str) {
+        self.u32(s.len() as u32);
+        self.out.extend_from_slice(s.as_bytes());
+    }
+
+    fn stamp(&mut self, st: &SourceStamp) {
+        self.u64(st.len);
+        self.u64(st.mtime_secs);
+        self.u32(st.mtime_nanos);
+    }
+
+    fn texture(&mut self, tex: &TextureCPU) {
+        self.u32(tex.w);
+        self.u32(tex.h);
+        self.u8(match tex.format() {
+            Format::R8 => 0,
+            Format::Rgb8 => 1,
+            Format::Rgba8 => 2,
+            Format::RgbF32 => 3,
+        });
+        self.u8(tex.srgb as u8);
+        self.u8(match tex.filter {
+            Filter::Nearest => 0,
+            Filter::Bilinear => 1,
+            Filter::Trilinear => 2,
+        });
+        self.u8(match tex.wrap {
+            Wrap::Clamp => 0,
+            Wrap::Repeat => 1,
+            Wrap::Mirror => 2,
+        });
+        match tex.base_texels() {
+            Texels::U8(data) => self.out.extend_from_slice(data),
+            Texels::F32(data) => data.iter().for_each(|&c| self.vec3(c)),
+        }
+    }
+}
+
+fn write_cache(cache: &Path, source: &Path, mesh: &ObjMesh) -> Result<(), Box<dyn Error>> {
+    let st = stamp(source).ok_or("sin fecha de modificación")?;
+    let hash = hash_bytes(&fs::read(source)?);
+    let dir = source.parent().unwrap_or(Path::new(""));
+
+    let floats = mesh.vertices.len() * 3 + mesh.normals.len() * 3 + mesh.uvs.len() * 2;
+    let mut w = Writer { out: Vec::with_capacity(64 + floats * 4 + mesh.indices.len() * 12) };
+    w.out.extend_from_slice(MAGIC);
+    w.u32(VERSION);
+    w.stamp(&st);
+    w.u64(hash);
+
+    w.u32(mesh.dependencies.len() as u32);
+    for dep in &mesh.dependencies {
+        w.str(&relative_to(dep, dir));
+        match stamp(dep) {
+            Some(st) => {
+                w.u8(1);
+                w.stamp(&st);
+            }
+            None => w.u8(0),
+        }
+    }
+
+    for count in [mesh.vertices.len(), mesh.normals.len(), mesh.uvs.len(), mesh.indices.len(), mesh.materials.len(), mesh.ranges.len()] {
+        w.u32(count as u32);
+    }
+    for &v in mesh.vertices.iter().chain(&mesh.normals) {
+        w.vec3(v);
+    }
+    for uv in &mesh.uvs {
+        w.f32(uv.x);
+        w.f32(uv.y);
+    }
+    for tri in &mesh.indices {
+        for &i in tri {
+            w.u32(i as u32);
+        }
+    }
+    for m in &mesh.materials {
+        w.str(&m.name);
+        w.vec3(m.diffuse);
+        w.vec3(m.specular);
+        w.f32(m.shininess);
+        w.vec3(m.emissive);
+        w.f32(m.opacity);
+        match &m.diffuse_file {
+            Some(file) => {
+                w.u8(1);
+                w.str(&relative_to(file, dir));
+            }
+            None => w.u8(0),
+        }
+        match &m.diffuse_map {
+            Some(tex) => {
+                w.u8(1);
+                w.texture(tex);
+            }
+            None => w.u8(0),
+        }
+    }
+    for r in &mesh.ranges {
+        w.u32(r.material.map_or(u32::MAX, |m| m as u32));
+        w.u32(r.triangles.start as u32);
+        w.u32(r.triangles.end as u32);
+    }
+    fs::write(cache, w.out)?;
+    Ok(())
+}
+
+/// Lector secuencial sobre los bytes del caché; `None` si se acaban.
+struct Reader<'a> {
+    bytes: &'a [u8],
+}
+
+impl<'a> Reader<'a> {
+    fn take<const N: usize>(&mut self) -> Option<[u8; N]> {
+        let (head, rest) = self.bytes.split_first_chunk::<N>()?;
+        self.bytes = rest;
+        Some(*head)
+    }
+
+    fn slice(&mut self, n: usize) -> Option<&'a [u8]> {
+        let (head, rest) = self.bytes.split_at_checked(n)?;
+        self.bytes = rest;
+        Some(head)
+    }
+
+    fn u8(&mut self) -> Option<u8> {
+        self.take::<1>().map(|[b]| b)
+    }
+
+    fn u32(&mut self) -> Option<u32> {
+        self.take().map(u32::from_le_bytes)
+    }
+
+    fn u64(&mut self) -> Option<u64> {
+        self.take().map(u64::from_le_bytes)
+    }
+
+    fn f32(&mut self) -> Option<f32> {
+        self.take().map(f32::from_le_bytes)
+    }
+
+    fn vec3(&mut self) -> Option<Vec3> {
+        Some(Vec3::new(self.f32()?, self.f32()?, self.f32()?))
+    }
+
+    /// Número de elementos de `item` bytes que siguen. Se comprueba que
+    /// quepan antes de reservar memoria: un caché truncado o corrupto se descarta.
+    fn count(&mut self, item: usize) -> Option<usize> {
+        let n = self.u32()? as usize;
+        (n.checked_mul(item)? <= self.bytes.len()).then_some(n)
+    }
+
+    fn vec3s(&mut self, n: usize) -> Option<Vec<Vec3>> {
+        (0..n).map(|_| self.vec3()).collect()
+    }
+
+    fn str(&mut self) -> Option<String> {
+        let n = self.count(1)?;
+        String::from_utf8(self.slice(n)?.to_vec()).ok()
+    }
+
+    fn stamp(&mut self) -> Option<SourceStamp> {
+        Some(SourceStamp { len: self.u64()?, mtime_secs: self.u64()?, mtime_nanos: self.u32()? })
+    }
+
+    fn texture(&mut self) -> Option<TextureCPU> {
+        let (w, h) = (self.u32()?, self.u32()?);
+        let format = match self.u8()? {
+            0 => Format::R8,
+            1 => Format::Rgb8,
+            2 => Format::Rgba8,
+            3 => Format::RgbF32,
+            _ => return None,
+        };
+        let srgb = self.u8()? != 0;
+        let filter = match self.u8()? {
+            0 => Filter::Nearest,
+            1 => Filter::Bilinear,
+            2 => Filter::Trilinear,
+            _ => return None,
+        };
+        let wrap = match self.u8()? {
+            0 => Wrap::Clamp,
+            1 => Wrap::Repeat,
+            2 => Wrap::Mirror,
+            _ => return None,
+        };
+        let texels = (w as usize).checked_mul(h as usize)?;
+        let mut tex = if format == Format::RgbF32 {
+            if texels.checked_mul(12)? > self.bytes.len() {
+                return None;
+            }
+            TextureCPU::new_hdr(w, h, self.vec3s(texels)?).ok()?
+        } else {
+            TextureCPU::new(w, h, format, self.slice(texels.checked_mul(format.channels())?)?.to_vec()).ok()?
+        };
+        tex.set_srgb(srgb);
+        tex.filter = filter;
+        tex.wrap = wrap;
+        Some(tex)
+    }
+}
+
+/// Lee el caché si existe, es de esta versión y corresponde a `source` y a
+/// sus dependencias.
+fn read_cache(cache: &Path, source: &Path) -> Option<ObjMesh> {
+    let bytes = fs::read(cache).ok()?;
+    let mut r = Reader { bytes: &bytes };
+    if &r.take::<4>()? != MAGIC || r.u32()? != VERSION {
+        return None;
+    }
+    let cached = r.stamp()?;
+    let hash = r.u64()?;
+    // Misma fecha y tamaño: no hace falta ni leer la fuente
+    let current = stamp(source)?;
+    let moved = current != cached;
+    if moved && hash_bytes(&fs::read(source).ok()?) != hash {
+        return None;
+    }
+
+    let dir = source.parent().unwrap_or(Path::new(""));
+    let mut dependencies = Vec::new();
+    for _ in 0..r.count(1)? {
+        let dep = dir.join(r.str()?);
+        let recorded = if r.u8()? != 0 { Some(r.stamp()?) } else { None };
+        if stamp(&dep) != recorded {
+            return None;
+        }
+        dependencies.push(dep);
+    }
+
+    let [nv, nn, nuv, nt, nm, nr] = [r.u32()?, r.u32()?, r.u32()?, r.u32()?, r.u32()?, r.u32()?].map(|n| n as usize);
+    // Lo de tamaño fijo tiene que caber antes de reservar memoria (los
+    // materiales miden lo que midan sus nombres y texturas)
+    if (nv + nn) * 12 + nuv * 8 + nt * 12 + nr * 12 > r.bytes.len() {
+        return None;
+    }
+    let vertices = r.vec3s(nv)?;
+    let normals = r.vec3s(nn)?;
+    let uvs = (0..nuv).map(|_| Some(Vec2::new(r.f32()?, r.f32()?))).collect::<Option<Vec<_>>>()?;
+    let indices = (0..nt)
+        .map(|_| {
+            let tri = [r.u32()? as usize, r.u32()? as usize, r.u32()? as usize];
+            tri.iter().all(|&i| i < nv).then_some(tri)
+        })
+        .collect::<Option<Vec<_>>>()?;
+    let materials = (0..nm)
+        .map(|_| {
+            let mut m = Material::new(&r.str()?);
+            m.diffuse = r.vec3()?;
+            m.specular = r.vec3()?;
+            m.shininess = r.f32()?;
+            m.emissive = r.vec3()?;
+            m.opacity = r.f32()?;
+            if r.u8()? != 0 {
+                m.diffuse_file = Some(dir.join(r.str()?));
+            }
+            if r.u8()? != 0 {
+                m.diffuse_map = Some(r.texture()?);
+            }
+            Some(m)
+        })
+        .collect::<Option<Vec<_>>>()?;
+    let ranges = (0..nr)
+        .map(|_| {
+            let material = match r.u32()? as usize {
+                m if m == u32::MAX as usize => None,
+                m if m < nm => Some(m),
+                _ => return None,
+            };
+            let triangles = r.u32()? as usize..r.u32()? as usize;
+            (triangles.start <= triangles.end && triangles.end <= nt).then_some(MaterialRange { material, triangles })
+        })
+        .collect::<Option<Vec<_>>>()?;
+    if !r.bytes.is_empty() {
+        return None;
+    }
+
+    if moved {
+        // Mismo contenido con otra fecha (una copia, un checkout): se
+        // actualiza la cabecera para no volver a leer la fuente la próxima vez
+        let mut w = Writer { out: Vec::new() };
+        w.stamp(&current);
+        let mut updated = bytes.clone();
+        updated[STAMP_OFFSET..STAMP_OFFSET + w.out.len()].copy_from_slice(&w.out);
+        let _ = fs::write(cache, updated);
+    }
+
+    let mut mesh = ObjMesh::new(vertices, indices);
+    mesh.normals = normals;
+    mesh.uvs = uvs;
+    mesh.materials = materials;
+    mesh.ranges = ranges;
+    mesh.dependencies = dependencies;
+    Some(mesh)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::TempDir;
+
+    #[test]
+    fn cache_round_trips_and_follows_source() {
+        let dir = TempDir::new("mesh_cache");
+        let obj = dir.write("quad.obj", "v 0 0 0\nv 1 0 0\nv 1 1 0\nv 0 1 0\nvt 0 0\nvn 0 0 1\nf 1/1/1 2/1/1 3/1/1 4/1/1\n");
+        let name = obj.to_string_lossy().to_string();
+
+        let parsed = load_cached(&name).unwrap();
+        assert!(cache_path(&name).exists());
+        let cached = read_cache(&cache_path(&name), &obj).expect("caché válido");
+        assert_eq!(cached.vertices, parsed.vertices);
+        assert_eq!(cached.normals, parsed.normals);
+        assert_eq!(cached.uvs, parsed.uvs);
+        assert_eq!(cached.indices, parsed.indices);
+
+        // Fuente distinta: el caché ya no vale y se regenera
+        fs::write(&obj, "v 0 0 0\nv 1 0 0\nv 0 1 0\nf 1 2 3\n").unwrap();
+        assert!(read_cache(&cache_path(&name), &obj).is_none());
+        assert_eq!(load_cached(&name).unwrap().indices.len(), 1);
+        assert_eq!(read_cache(&cache_path(&name), &obj).unwrap().indices.len(), 1);
+
+        // Mismo contenido con otra fecha: vale, y la cabecera queda al día
+        let later = std::time::SystemTime::now() + std::time::Duration::from_secs(60);
+        fs::File::options().write(true).open(&obj).unwrap().set_modified(later).unwrap();
+        assert!(read_cache(&cache_path(&name), &obj).is_some());
+        let mut w = Writer { out: Vec::new() };
+        w.stamp(&stamp(&obj).unwrap());
+        assert_eq!(fs::read(cache_path(&name)).unwrap()[STAMP_OFFSET..STAMP_OFFSET + w.out.len()], w.out[..]);
+
+        // Caché corrupto: se ignora
+        fs::write(cache_path(&name), b"STMC\x02\0\0\0basura").unwrap();
+        assert!(read_cache(&cache_path(&name), &obj).is_none());
+    }
+
+    #[test]
+    fn cache_keeps_materials_and_follows_dependencies() {
+        let dir = TempDir::new("mesh_cache_mtl");
+        let obj = dir.write("tri.obj", "mtllib tri.mtl\nv 0 0 0\nv 1 0 0\nv 0 1 0\nvt 0 0\nusemtl Rojo\nf 1/1 2/1 3/1\n");
+        let name = obj.to_string_lossy().to_string();
+
+        // Sin el .mtl se cachea sin materiales, anotando que faltaba
+        assert!(load_cached(&name).unwrap().materials.is_empty());
+        assert!(read_cache(&cache_path(&name), &obj).is_some());
+
+        // Al aparecer, el caché deja de valer; el nuevo guarda material y textura
+        let mut hdr = b"#?RADIANCE\n\n-Y 1 +X 1\n".to_vec();
+        hdr.extend([128, 64, 0, 129]);
+        dir.write("lava.hdr", hdr);
+        dir.write("tri.mtl", "newmtl Rojo\nKd 1 0 0\nmap_Kd lava.hdr\n");
+        assert!(read_cache(&cache_path(&name), &obj).is_none());
+        let mesh = load_cached(&name).unwrap();
+        let cached = read_cache(&cache_path(&name), &obj).expect("caché con materiales");
+        assert_eq!(cached.ranges, mesh.ranges);
+        assert_eq!(cached.dependencies, vec![dir.join("tri.mtl"), dir.join("lava.hdr")]);
+        assert_eq!(cached.materials[0].diffuse, Vec3::X);
+        assert_eq!(cached.materials[0].diffuse_file, Some(dir.join("lava.hdr")));
+        let tex = cached.materials[0].diffuse_map.as_ref().expect("textura cacheada");
+        assert_eq!(tex.sample(0.5, 0.5), Vec3::new(1.0, 0.5, 0.0));
+        assert_eq!(tex.filter, Filter::Trilinear);
+
+        // Cambiar la textura también lo invalida
+        dir.write("lava.hdr", b"roto");
+        assert!(read_cache(&cache_path(&name), &obj).is_none());
+    }
+
+    #[test]
+    fn textures_round_trip() {
+        let mut tex = TextureCPU::new(2, 1, Format::Rgba8, vec![255, 0, 0, 64, 0, 128, 255, 255]).unwrap();
+        tex.set_srgb(false);
+        tex.filter = Filter::Nearest;
+        tex.wrap = Wrap::Mirror;
+        let mut w = Writer { out: Vec::new() };
+        w.texture(&tex);
+        let back = Reader { bytes: &w.out }.texture().unwrap();
+        assert_eq!((back.w, back.h, back.format(), back.srgb), (2, 1, Format::Rgba8, false));
+        assert_eq!((back.filter, back.wrap), (Filter::Nearest, Wrap::Mirror));
+        for u in [0.25, 0.75, 1.25] {
+            assert_eq!(back.sample_rgba_lod(u, 0.5, 0.0), tex.sample_rgba_lod(u, 0.5, 0.0));
+        }
+        assert!(Reader { bytes: &w.out[..w.out.len() - 1] }.texture().is_none());
+    }
+}
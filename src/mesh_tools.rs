@@ -0,0 +1,450 @@
+use glam::{DVec3, Vec2, Vec3};
+use std::cmp::Ordering;
+use std::collections::{BinaryHeap, HashMap};
+
+use crate::object::{BoundingSphere, MaterialRange, ObjMesh};
+
+impl BoundingSphere {
+    /// Esfera de Ritter: parte del par de puntos más alejados aproximado y
+    /// crece para incluir los que se salgan. Suele ser más ajustada que
+    /// `from_points` en mallas alargadas (naves).
+    pub fn ritter(points: &[Vec3]) -> Self {
+        let Some(&first) = points.first() else {
+            return Self { center: Vec3::ZERO, radius: 0.0 };
+        };
+        let farthest = |from: Vec3| *points.iter().max_by(|a, b| a.distance_squared(from).total_cmp(&b.distance_squared(from))).unwrap();
+        let a = farthest(first);
+        let b = farthest(a);
+        let mut center = (a + b) * 0.5;
+        let mut radius = a.distance(b) * 0.5;
+        for p in points {
+            let d = p.distance(center);
+            if d > radius {
+                let new_radius = (radius + d) * 0.5;
+                center += (*p - center) * ((new_radius - radius) / d);
+                radius = new_radius;
+            }
+        }
+        // Margen para el redondeo: ningún punto debe quedar fuera
+        let radius = points.iter().map(|p| p.distance(center)).fold(radius, f32::max);
+        Self { center, radius }
+    }
+}
+
+impl ObjMesh {
+    /// Recalcula `bounds` con la menor de la esfera centrada en la caja y la de Ritter.
+    pub fn compute_bounds(&mut self) {
+        let boxed = BoundingSphere::from_points(&self.vertices);
+        let ritter = BoundingSphere::ritter(&self.vertices);
+        self.bounds = if ritter.radius < boxed.radius { ritter } else { boxed };
+    }
+
+    /// Une los vértices a menos de `tolerance` entre sí (y con las mismas UV,
+    /// si las hay, para no coser costuras de textura) y quita los triángulos
+    /// que quedan degenerados. El vértice unido conserva la normal del primero:
+    /// conviene llamar a `compute_normals` después. Devuelve cuántos vértices
+    /// se eliminaron.
+    pub fn weld(&mut self, tolerance: f32) -> usize {
+        let tolerance = tolerance.max(f32::EPSILON);
+        let cell = |p: Vec3| (p / tolerance).floor().as_ivec3();
+        let mut grid: HashMap<glam::IVec3, Vec<usize>> = HashMap::new();
+        let mut remap = vec![0usize; self.vertices.len()];
+        let mut kept: Vec<usize> = Vec::new();
+
+        for (i, &p) in self.vertices.iter().enumerate() {
+            let c = cell(p);
+            let mut found = None;
+            'search: for dz in -1..=1 {
+                for dy in -1..=1 {
+                    for dx in -1..=1 {
+                        let Some(list) = grid.get(&(c + glam::IVec3::new(dx, dy, dz))) else { continue };
+                        for &k in list {
+                            let j = kept[k];
+                            let same_uv = self.uvs.is_empty() || self.uvs[i].distance(self.uvs[j]) <= tolerance;
+                            if p.distance(self.vertices[j]) <= tolerance && same_uv {
+                                found = Some(k);
+                                break 'search;
+                            }
+                        }
+                    }
+                }
+            }
+            remap[i] = found.unwrap_or_else(|| {
+                kept.push(i);
+                grid.entry(c).or_default().push(kept.len() - 1);
+                kept.len() - 1
+            });
+        }
+
+        let removed = self.vertices.len() - kept.len();
+        self.vertices = kept.iter().map(|&i| self.vertices[i]).collect();
+        if !self.normals.is_empty() {
+            self.normals = kept.iter().map(|&i| self.normals[i]).collect();
+        }
+        if !self.uvs.is_empty() {
+            self.uvs = kept.iter().map(|&i| self.uvs[i]).collect();
+        }
+        let alive: Vec<bool> = self
+            .indices
+            .iter_mut()
+            .map(|tri| {
+                *tri = tri.map(|i| remap[i]);
+                tri[0] != tri[1] && tri[1] != tri[2] && tri[2] != tri[0]
+            })
+            .collect();
+        self.retain_triangles(&alive);
+        removed
+    }
+
+    /// Normales suaves por vértice: media de las normales de las caras que lo
+    /// usan, pesadas por el ángulo de cada cara en el vértice (así no dependen
+    /// de cómo esté triangulada). Los vértices duplicados (costuras) no se
+    /// promedian entre sí; usar `weld` antes si hace falta.
+    pub fn compute_normals(&mut self) {
+        let mut normals = vec![Vec3::ZERO; self.vertices.len()];
+        for tri in &self.indices {
+            let p = tri.map(|i| self.vertices[i]);
+            let n = (p[1] - p[0]).cross(p[2] - p[0]).normalize_or_zero();
+            for k in 0..3 {
+                let e1 = (p[(k + 1) % 3] - p[k]).normalize_or_zero();
+                let e2 = (p[(k + 2) % 3] - p[k]).normalize_or_zero();
+                normals[tri[k]] += n * e1.dot(e2).clamp(-1.0, 1.0).acos();
+            }
+        }
+        self.normals = normals.into_iter().map(|n| n.normalize_or_zero()).collect();
+    }
+
+    /// Simplifica la malla hasta `target` triángulos (o lo más cerca posible)
+    /// colapsando aristas por métrica de error cuadrática (Garland-Heckbert).
+    /// Cada colapso se queda con el extremo o el punto medio de menor error;
+    /// los bordes abiertos y las costuras de UV se penalizan para que no se
+    /// encojan, y se rechazan los colapsos que darían la vuelta a una cara.
+    /// Necesita vértices compartidos: llamar a `weld` antes si la malla trae
+    /// un vértice por esquina.
+    pub fn simplify(&mut self, target: usize) {
+        let tris_before = self.indices.len();
+        if target >= tris_before {
+            return;
+        }
+        let mut s = Simplifier::new(self);
+        s.run(self, target);
+        let alive: Vec<bool> = s.alive_tris.clone();
+        s.apply(self);
+        self.retain_triangles(&alive);
+        self.compact_vertices();
+        self.bounds = BoundingSphere::from_points(&self.vertices);
+    }
+
+    /// Quita los triángulos con `alive[t] == false`, ajustando los tramos de material.
+    fn retain_triangles(&mut self, alive: &[bool]) {
+        let mut kept_before = Vec::with_capacity(alive.len() + 1);
+        let mut count = 0;
+        for &a in alive {
+            kept_before.push(count);
+            count += a as usize;
+        }
+        kept_before.push(count);
+        for r in self.ranges.iter_mut() {
+            r.triangles = kept_before[r.triangles.start]..kept_before[r.triangles.end];
+        }
+        self.ranges.retain(|r: &MaterialRange| !r.triangles.is_empty());
+        let mut t = 0;
+        self.indices.retain(|_| {
+            t += 1;
+            alive[t - 1]
+        });
+    }
+
+    /// Quita los vértices que ningún triángulo usa.
+    fn compact_vertices(&mut self) {
+        let mut remap = vec![usize::MAX; self.vertices.len()];
+        let mut order = Vec::new();
+        for tri in self.indices.iter_mut() {
+            for i in tri.iter_mut() {
+                if remap[*i] == usize::MAX {
+                    remap[*i] = order.len();
+                    order.push(*i);
+                }
+                *i = remap[*i];
+            }
+        }
+        self.vertices = order.iter().map(|&i| self.vertices[i]).collect();
+        if !self.normals.is_empty() {
+            self.normals = order.iter().map(|&i| self.normals[i]).collect();
+        }
+        if !self.uvs.is_empty() {
+            self.uvs = order.iter().map(|&i| self.uvs[i]).collect();
+        }
+    }
+}
+
+/// Cuádrica simétrica 4x4 (10 coeficientes) en f64.
+#[derive(Copy, Clone, Default)]
+struct Quadric([f64; 10]);
+
+impl Quadric {
+    /// Distancia al cuadrado al plano n·x + d = 0, multiplicada por `weight`.
+    fn plane(n: DVec3, d: f64, weight: f64) -> Self {
+        let (a, b, c) = (n.x, n.y, n.z);
+        Self([a * a, a * b, a * c, a * d, b * b, b * c, b * d, c * c, c * d, d * d].map(|v| v * weight))
+    }
+
+    fn add(&mut self, o: &Quadric) {
+        for (a, b) in self.0.iter_mut().zip(o.0) {
+            *a += b;
+        }
+    }
+
+    fn error(&self, p: DVec3) -> f64 {
+        let q = &self.0;
+        let (x, y, z) = (p.x, p.y, p.z);
+        q[0] * x * x + 2.0 * q[1] * x * y + 2.0 * q[2] * x * z + 2.0 * q[3] * x
+            + q[4] * y * y + 2.0 * q[5] * y * z + 2.0 * q[6] * y
+            + q[7] * z * z + 2.0 * q[8] * z
+            + q[9]
+    }
+}
+
+/// Candidato a colapso en el montículo. `stamps` detecta entradas obsoletas
+/// (algún extremo cambió desde que se calculó el coste).
+struct Collapse {
+    cost: f64,
+    a: usize,
+    b: usize,
+    t: f32,
+    stamps: (u32, u32),
+}
+
+impl PartialEq for Collapse {
+    fn eq(&self, other: &Self) -> bool {
+        self.cost == other.cost
+    }
+}
+impl Eq for Collapse {}
+impl PartialOrd for Collapse {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+impl Ord for Collapse {
+    // Orden inverso: BinaryHeap saca primero el de menor coste
+    fn cmp(&self, other: &Self) -> Ordering {
+        other.cost.total_cmp(&self.cost)
+    }
+}
+
+struct Simplifier {
+    positions: Vec<DVec3>,
+    normals: Vec<Vec3>,
+    uvs: Vec<Vec2>,
+    quadrics: Vec<Quadric>,
+    /// Triángulos que usan cada vértice (incluye muertos; se filtran al usarlos)
+    vertex_tris: Vec<Vec<usize>>,
+    tris: Vec<[usize; 3]>,
+    alive_tris: Vec<bool>,
+    stamps: Vec<u32>,
+    heap: BinaryHeap<Collapse>,
+}
+
+impl Simplifier {
+    fn new(mesh: &ObjMesh) -> Self {
+        let positions: Vec<DVec3> = mesh.vertices.iter().map(|v| v.as_dvec3()).collect();
+        let mut quadrics = vec![Quadric::default(); positions.len()];
+        let mut vertex_tris = vec![Vec::new(); positions.len()];
+        let mut edge_count: HashMap<(usize, usize), (u32, usize)> = HashMap::new();
+
+        for (t, tri) in mesh.indices.iter().enumerate() {
+            let p = tri.map(|i| positions[i]);
+            let n = (p[1] - p[0]).cross(p[2] - p[0]).normalize_or_zero();
+            let q = Quadric::plane(n, -n.dot(p[0]), 1.0);
+            for k in 0..3 {
+                quadrics[tri[k]].add(&q);
+                vertex_tris[tri[k]].push(t);
+                let (a, b) = (tri[k], tri[(k + 1) % 3]);
+                edge_count.entry((a.min(b), a.max(b))).or_insert((0, t)).0 += 1;
+            }
+        }
+
+        // Bordes: plano perpendicular a la cara por la arista, con peso alto
+        for (&(a, b), &(count, t)) in &edge_count {
+            if count != 1 {
+                continue;
+            }
+            let tri = mesh.indices[t];
+            let p = tri.map(|i| positions[i]);
+            let face_n = (p[1] - p[0]).cross(p[2] - p[0]).normalize_or_zero();
+            let edge = positions[b] - positions[a];
+            let n = edge.cross(face_n).normalize_or_zero();
+            let q = Quadric::plane(n, -n.dot(positions[a]), 1000.0 * edge.length_squared().max(1e-12));
+            quadrics[a].add(&q);
+            quadrics[b].add(&q);
+        }
+
+        let mut s = Self {
+            positions,
+            normals: mesh.normals.clone(),
+            uvs: mesh.uvs.clone(),
+            quadrics,
+            vertex_tris,
+            tris: mesh.indices.clone(),
+            alive_tris: vec![true; mesh.indices.len()],
+            stamps: vec![0; mesh.vertices.len()],
+            heap: BinaryHeap::new(),
+        };
+        for &(a, b) in edge_count.keys() {
+            s.push_edge(a, b);
+        }
+        s
+    }
+
+    fn push_edge(&mut self, a: usize, b: usize) {
+        let mut q = self.quadrics[a];
+        q.add(&self.quadrics[b]);
+        let (cost, t) = [0.0, 1.0, 0.5]
+            .map(|t| (q.error(self.positions[a].lerp(self.positions[b], t as f64)), t))
+            .into_iter()
+            .min_by(|x, y| x.0.total_cmp(&y.0))
+            .unwrap();
+        self.heap.push(Collapse { cost, a, b, t, stamps: (self.stamps[a], self.stamps[b]) });
+    }
+
+    /// ¿Da la vuelta alguna cara de `v` (salvo las compartidas con `other`) al moverlo a `to`?
+    fn flips(&self, v: usize, other: usize, to: DVec3) -> bool {
+        self.vertex_tris[v].iter().filter(|&&t| self.alive_tris[t]).any(|&t| {
+            let tri = self.tris[t];
+            if tri.contains(&other) {
+                return false;
+            }
+            let p = tri.map(|i| self.positions[i]);
+            let before = (p[1] - p[0]).cross(p[2] - p[0]);
+            let q = tri.map(|i| if i == v { to } else { self.positions[i] });
+            let after = (q[1] - q[0]).cross(q[2] - q[0]);
+            before.dot(after) <= 0.0
+        })
+    }
+
+    fn run(&mut self, mesh: &ObjMesh, target: usize) {
+        let mut alive = mesh.indices.len();
+        while alive > target {
+            let Some(c) = self.heap.pop() else { break };
+            if (self.stamps[c.a], self.stamps[c.b]) != c.stamps {
+                continue;
+            }
+            let (a, b) = (c.a, c.b);
+            let to = self.positions[a].lerp(self.positions[b], c.t as f64);
+            if self.flips(a, b, to) || self.flips(b, a, to) {
+                continue;
+            }
+
+            // `b` se une a `a`: las caras con ambos desaparecen, el resto pasa a usar `a`
+            self.positions[a] = to;
+            if !self.normals.is_empty() {
+                self.normals[a] = self.normals[a].lerp(self.normals[b], c.t).normalize_or_zero();
+            }
+            if !self.uvs.is_empty() {
+                self.uvs[a] = self.uvs[a].lerp(self.uvs[b], c.t);
+            }
+            let qb = self.quadrics[b];
+            self.quadrics[a].add(&qb);
+            let moved = std::mem::take(&mut self.vertex_tris[b]);
+            for t in moved {
+                if !self.alive_tris[t] {
+                    continue;
+                }
+                if self.tris[t].contains(&a) {
+                    self.alive_tris[t] = false;
+                    alive -= 1;
+                } else {
+                    for i in self.tris[t].iter_mut() {
+                        if *i == b {
+                            *i = a;
+                        }
+                    }
+                    self.vertex_tris[a].push(t);
+                }
+            }
+            self.vertex_tris[a].retain(|&t| self.alive_tris[t]);
+            self.stamps[a] += 1;
+            self.stamps[b] += 1;
+
+            let mut neighbours: Vec<usize> = self.vertex_tris[a].iter().flat_map(|&t| self.tris[t]).filter(|&i| i != a).collect();
+            neighbours.sort_unstable();
+            neighbours.dedup();
+            for n in neighbours {
+                self.push_edge(a, n);
+            }
+        }
+    }
+
+    /// Copia a la malla las posiciones y atributos movidos por los colapsos.
+    fn apply(self, mesh: &mut ObjMesh) {
+        mesh.vertices = self.positions.iter().map(|p| p.as_vec3()).collect();
+        mesh.normals = self.normals;
+        mesh.uvs = self.uvs;
+        mesh.indices = self.tris;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Malla con un vértice por esquina, como sphere.obj (normales planas).
+    fn unwelded(mesh: &ObjMesh) -> ObjMesh {
+        let vertices: Vec<Vec3> = mesh.indices.iter().flat_map(|t| t.map(|i| mesh.vertices[i])).collect();
+        let indices = (0..mesh.indices.len()).map(|t| [3 * t, 3 * t + 1, 3 * t + 2]).collect();
+        ObjMesh::new(vertices, indices)
+    }
+
+    #[test]
+    fn weld_restores_shared_vertices() {
+        let sphere = ObjMesh::icosphere(2, 1.0);
+        let mut split = unwelded(&sphere);
+        assert_eq!(split.vertices.len(), sphere.indices.len() * 3);
+        split.weld(1e-4);
+        assert_eq!(split.vertices.len(), sphere.vertices.len());
+        assert_eq!(split.indices.len(), sphere.indices.len());
+    }
+
+    #[test]
+    fn smooth_normals_point_outwards_on_sphere() {
+        let mut sphere = ObjMesh::icosphere(3, 2.0);
+        sphere.compute_normals();
+        for (v, n) in sphere.vertices.iter().zip(&sphere.normals) {
+            assert!(v.normalize().dot(*n) > 0.995);
+        }
+    }
+
+    #[test]
+    fn ritter_sphere_is_tighter_on_long_shapes() {
+        let mut points: Vec<Vec3> = (0..=10).map(|i| Vec3::new(i as f32, 0.0, 0.0)).collect();
+        points.push(Vec3::new(0.0, 0.5, 0.0));
+        let ritter = BoundingSphere::ritter(&points);
+        assert!(points.iter().all(|p| p.distance(ritter.center) <= ritter.radius + 1e-4));
+        assert!(ritter.radius <= BoundingSphere::from_points(&points).radius + 1e-4);
+    }
+
+    #[test]
+    fn simplify_reaches_target_and_keeps_shape() {
+        let mut sphere = ObjMesh::icosphere(4, 1.0);
+        sphere.simplify(500);
+        assert!(sphere.indices.len() <= 500 && sphere.indices.len() > 400, "{}", sphere.indices.len());
+        // Sigue siendo una esfera: vértices cerca del radio y caras hacia fuera
+        assert!(sphere.vertices.iter().all(|v| (v.length() - 1.0).abs() < 0.1));
+        for tri in &sphere.indices {
+            let p = tri.map(|i| sphere.vertices[i]);
+            assert!((p[1] - p[0]).cross(p[2] - p[0]).dot(p[0] + p[1] + p[2]) > 0.0);
+        }
+        assert!(sphere.indices.iter().flatten().all(|&i| i < sphere.vertices.len()));
+    }
+
+    #[test]
+    fn simplify_keeps_open_border() {
+        let mut disk = ObjMesh::annulus(1.0, 2.0, 64);
+        let extent = |m: &ObjMesh| m.vertices.iter().fold(Vec3::ZERO, |e, v| e.max(v.abs()));
+        let before = extent(&disk);
+        disk.simplify(40);
+        assert!(disk.indices.len() <= 40);
+        assert!((extent(&disk) - before).abs().max_element() < 0.1);
+    }
+}
@@ -1,51 +1,602 @@
-use glam::Vec3;
+use glam::{Vec2, Vec3};
+use std::collections::HashMap;
+use std::f32::consts::{PI, TAU};
+use std::fmt;
 use std::fs;
+use std::ops::Range;
+use std::path::{Path, PathBuf};
 
+use crate::gltf_import::load_gltf;
+use crate::material::{load_mtl, Material};
+
+/// Qué caras descarta el rasterizador según su orientación en pantalla.
+/// Las caras frontales son las que tienen el orden de vértices del OBJ
+/// (antihorario visto desde fuera).
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum CullMode {
+    /// Descartar caras traseras (mallas cerradas como las esferas)
+    Back,
+    /// Descartar caras frontales (p. ej. ver el interior de una cúpula)
+    Front,
+    /// Dibujar ambas caras (mallas abiertas o con orientación inconsistente)
+    None,
+}
+
+/// Esfera envolvente en el espacio del objeto.
+#[derive(Copy, Clone, Debug)]
+pub struct BoundingSphere {
+    pub center: Vec3,
+    pub radius: f32,
+}
+
+impl BoundingSphere {
+    /// Esfera centrada en el centro de la caja envolvente, con el radio justo
+    /// para contener todos los puntos (no es mínima, pero sí ajustada).
+    pub fn from_points(points: &[Vec3]) -> Self {
+        if points.is_empty() {
+            return Self { center: Vec3::ZERO, radius: 0.0 };
+        }
+        let (min, max) = points
+            .iter()
+            .fold((Vec3::splat(f32::MAX), Vec3::splat(f32::MIN)), |(lo, hi), p| (lo.min(*p), hi.max(*p)));
+        let center = (min + max) * 0.5;
+        let radius = points.iter().map(|p| p.distance(center)).fold(0.0, f32::max);
+        Self { center, radius }
+    }
+}
+
+/// Tramo de triángulos consecutivos (índices en `ObjMesh::indices`) que
+/// comparten material. `None` = sin material: se usa el shader del cuerpo.
+#[derive(Clone, Debug, PartialEq)]
+pub struct MaterialRange {
+    pub material: Option<usize>,
+    pub triangles: Range<usize>,
+}
+
+#[derive(Clone)]
 pub struct ObjMesh {
     pub vertices: Vec<Vec3>,
+    /// Normal por vértice (vacío si la malla no la trae)
+    pub normals: Vec<Vec3>,
+    /// Coordenadas de textura por vértice (vacío si la malla no las trae)
+    pub uvs: Vec<Vec2>,
     pub indices: Vec<[usize; 3]>,
+    /// Materiales de los `mtllib` del OBJ
+    pub materials: Vec<Material>,
+    /// Vacío si ningún triángulo tiene material
+    pub ranges: Vec<MaterialRange>,
+    /// Otros archivos de los que sale la malla (`.mtl`, texturas, `.bin`),
+    /// también los que faltaban al cargarla
+    pub dependencies: Vec<PathBuf>,
+    pub cull: CullMode,
+    pub bounds: BoundingSphere,
 }
 
 impl ObjMesh {
-    pub fn load(path: &str) -> Self {
-        let text = fs::read_to_string(path)
-            .expect(&format!("No se pudo leer {}", path));
+    /// Malla a partir de vértices y triángulos ya construidos.
+    pub fn new(vertices: Vec<Vec3>, indices: Vec<[usize; 3]>) -> Self {
+        let bounds = BoundingSphere::from_points(&vertices);
+        Self {
+            vertices,
+            normals: Vec::new(),
+            uvs: Vec::new(),
+            indices,
+            materials: Vec::new(),
+            ranges: Vec::new(),
+            dependencies: Vec::new(),
+            cull: CullMode::Back,
+            bounds,
+        }
+    }
+
+    /// Icosfera de radio `radius`: un icosaedro con cada triángulo dividido
+    /// en 4 `subdivisions` veces (20 * 4^n triángulos), proyectado a la esfera.
+    /// Caras en sentido antihorario vistas desde fuera, como los OBJ.
+    /// Las UV son esféricas (como en `uv_sphere`) pero sin costura duplicada,
+    /// así que los triángulos que cruzan u = 0 estiran la textura.
+    #[cfg(test)]
+    pub fn icosphere(subdivisions: u32, radius: f32) -> Self {
+        let t = (1.0 + 5.0_f32.sqrt()) * 0.5;
+        let mut vertices: Vec<Vec3> = [
+            (-1.0, t, 0.0), (1.0, t, 0.0), (-1.0, -t, 0.0), (1.0, -t, 0.0),
+            (0.0, -1.0, t), (0.0, 1.0, t), (0.0, -1.0, -t), (0.0, 1.0, -t),
+            (t, 0.0, -1.0), (t, 0.0, 1.0), (-t, 0.0, -1.0), (-t, 0.0, 1.0),
+        ]
+        .iter()
+        .map(|&(x, y, z)| Vec3::new(x, y, z).normalize())
+        .collect();
+        let mut indices: Vec<[usize; 3]> = vec![
+            [0, 11, 5], [0, 5, 1], [0, 1, 7], [0, 7, 10], [0, 10, 11],
+            [1, 5, 9], [5, 11, 4], [11, 10, 2], [10, 7, 6], [7, 1, 8],
+            [3, 9, 4], [3, 4, 2], [3, 2, 6], [3, 6, 8], [3, 8, 9],
+            [4, 9, 5], [2, 4, 11], [6, 2, 10], [8, 6, 7], [9, 8, 1],
+        ];
+
+        for _ in 0..subdivisions {
+            // Punto medio de cada arista, compartido entre los dos triángulos vecinos
+            let mut midpoints: HashMap<(usize, usize), usize> = HashMap::new();
+            let mut midpoint = |a: usize, b: usize, vertices: &mut Vec<Vec3>| -> usize {
+                let key = (a.min(b), a.max(b));
+                *midpoints.entry(key).or_insert_with(|| {
+                    vertices.push(((vertices[a] + vertices[b]) * 0.5).normalize());
+                    vertices.len() - 1
+                })
+            };
+            let mut next = Vec::with_capacity(indices.len() * 4);
+            for [a, b, c] in indices {
+                let ab = midpoint(a, b, &mut vertices);
+                let bc = midpoint(b, c, &mut vertices);
+                let ca = midpoint(c, a, &mut vertices);
+                next.push([a, ab, ca]);
+                next.push([b, bc, ab]);
+                next.push([c, ca, bc]);
+                next.push([ab, bc, ca]);
+            }
+            indices = next;
+        }
+
+        let uvs = vertices
+            .iter()
+            .map(|n| Vec2::new((n.z.atan2(n.x) / TAU).rem_euclid(1.0), n.y.clamp(-1.0, 1.0).acos() / PI))
+            .collect();
+        let normals = vertices.clone();
+        for v in vertices.iter_mut() {
+            *v *= radius;
+        }
+        Self::new(vertices, indices).with_attributes(normals, uvs)
+    }
+
+    /// Esfera UV de radio `radius`: `rings` paralelos entre los polos y
+    /// `segments` meridianos. La costura (u = 0 y u = 1) duplica vértices
+    /// para que las UV no salten; los triángulos degenerados de los polos
+    /// se descartan.
+    pub fn uv_sphere(segments: u32, rings: u32, radius: f32) -> Self {
+        let (segments, rings) = (segments.max(3), rings.max(2));
+        let mut normals = Vec::new();
+        let mut uvs = Vec::new();
+        for r in 0..=rings {
+            let v = r as f32 / rings as f32;
+            let theta = v * PI;
+            for s in 0..=segments {
+                let u = s as f32 / segments as f32;
+                let phi = u * TAU;
+                normals.push(Vec3::new(theta.sin() * phi.cos(), theta.cos(), theta.sin() * phi.sin()));
+                uvs.push(Vec2::new(u, v));
+            }
+        }
+        let vertices = normals.iter().map(|n| *n * radius).collect();
+        Self::from_grid(vertices, normals, uvs, rings, segments)
+    }
+
+    /// Toro centrado en el origen alrededor del eje Y: `major` es la distancia
+    /// al centro del tubo y `minor` su radio.
+    #[cfg(test)]
+    pub fn torus(major: f32, minor: f32, major_segments: u32, minor_segments: u32) -> Self {
+        let (major_segments, minor_segments) = (major_segments.max(3), minor_segments.max(3));
+        let mut vertices = Vec::new();
+        let mut normals = Vec::new();
+        let mut uvs = Vec::new();
+        for i in 0..=major_segments {
+            let u = i as f32 / major_segments as f32;
+            let (su, cu) = (u * TAU).sin_cos();
+            for j in 0..=minor_segments {
+                let v = j as f32 / minor_segments as f32;
+                let (sv, cv) = (v * TAU).sin_cos();
+                let n = Vec3::new(cv * cu, sv, cv * su);
+                vertices.push(Vec3::new(major * cu, 0.0, major * su) + n * minor);
+                normals.push(n);
+                uvs.push(Vec2::new(u, v));
+            }
+        }
+        Self::from_grid(vertices, normals, uvs, major_segments, minor_segments)
+    }
 
-        let mut vertices = vec![];
-        let mut indices = vec![];
+    /// Corona circular en el plano XZ con normal +Y (la geometría de los
+    /// anillos). La U recorre el ángulo y la V va del borde interior (0) al
+    /// exterior (1). Se dibuja por ambas caras.
+    pub fn annulus(inner: f32, outer: f32, segments: u32) -> Self {
+        let segments = segments.max(3);
+        let mut vertices = Vec::new();
+        let mut uvs = Vec::new();
+        for (v, r) in [(0.0, inner), (1.0, outer)] {
+            for s in 0..=segments {
+                let u = s as f32 / segments as f32;
+                let (sa, ca) = (u * TAU).sin_cos();
+                vertices.push(Vec3::new(r * ca, 0.0, r * sa));
+                uvs.push(Vec2::new(u, v));
+            }
+        }
+        let normals = vec![Vec3::Y; vertices.len()];
+        let mut mesh = Self::from_grid(vertices, normals, uvs, 1, segments);
+        mesh.cull = CullMode::None;
+        mesh
+    }
 
-        for line in text.lines() {
-            let parts: Vec<&str> = line.split_whitespace().collect();
-            if parts.is_empty() { continue; }
+    /// Disco de radio `radius` en el plano XZ (una corona sin hueco).
+    #[cfg(test)]
+    pub fn disk(radius: f32, segments: u32) -> Self {
+        Self::annulus(0.0, radius, segments)
+    }
+
+    /// Cubo de lado `size` centrado en el origen, con 4 vértices por cara para
+    /// que cada una tenga su normal y sus UV completas.
+    pub fn cube(size: f32) -> Self {
+        let h = size * 0.5;
+        // (normal, u, v) con u x v = normal
+        let faces = [
+            (Vec3::X, Vec3::NEG_Z, Vec3::Y),
+            (Vec3::NEG_X, Vec3::Z, Vec3::Y),
+            (Vec3::Y, Vec3::X, Vec3::NEG_Z),
+            (Vec3::NEG_Y, Vec3::X, Vec3::Z),
+            (Vec3::Z, Vec3::X, Vec3::Y),
+            (Vec3::NEG_Z, Vec3::NEG_X, Vec3::Y),
+        ];
+        let corners = [(-1.0, -1.0, 0.0, 1.0), (1.0, -1.0, 1.0, 1.0), (1.0, 1.0, 1.0, 0.0), (-1.0, 1.0, 0.0, 0.0)];
+        let mut vertices = Vec::new();
+        let mut normals = Vec::new();
+        let mut uvs = Vec::new();
+        let mut indices = Vec::new();
+        for (n, u, v) in faces {
+            let base = vertices.len();
+            for (cu, cv, tu, tv) in corners {
+                vertices.push((n + u * cu + v * cv) * h);
+                normals.push(n);
+                uvs.push(Vec2::new(tu, tv));
+            }
+            indices.push([base, base + 1, base + 2]);
+            indices.push([base, base + 2, base + 3]);
+        }
+        Self::new(vertices, indices).with_attributes(normals, uvs)
+    }
 
-            match parts[0] {
+    /// Triangula una rejilla de `(rows + 1) * (cols + 1)` vértices guardada
+    /// por filas. La orientación sale antihoraria vista desde fuera si
+    /// d(columna) x d(fila) apunta hacia fuera, como en todas las superficies
+    /// de arriba. Descarta los triángulos degenerados (polos, disco).
+    fn from_grid(vertices: Vec<Vec3>, normals: Vec<Vec3>, uvs: Vec<Vec2>, rows: u32, cols: u32) -> Self {
+        let stride = cols as usize + 1;
+        let mut indices = Vec::with_capacity(rows as usize * cols as usize * 2);
+        for i in 0..rows as usize {
+            for j in 0..cols as usize {
+                let a = i * stride + j;
+                let b = a + stride;
+                let c = b + 1;
+                let d = a + 1;
+                for tri in [[a, c, b], [a, d, c]] {
+                    let [p0, p1, p2] = tri.map(|k| vertices[k]);
+                    if (p1 - p0).cross(p2 - p0).length_squared() > 1e-12 {
+                        indices.push(tri);
+                    }
+                }
+            }
+        }
+        Self::new(vertices, indices).with_attributes(normals, uvs)
+    }
+
+    fn with_attributes(mut self, normals: Vec<Vec3>, uvs: Vec<Vec2>) -> Self {
+        debug_assert_eq!(normals.len(), self.vertices.len());
+        debug_assert_eq!(uvs.len(), self.vertices.len());
+        self.normals = normals;
+        self.uvs = uvs;
+        self
+    }
+
+    /// Lee un `.obj`. Las caras de más de tres vértices se triangulan en
+    /// abanico y cada combinación distinta `v/vt/vn` se convierte en un
+    /// vértice propio. Las normales y UV solo se guardan si todas las
+    /// esquinas las traen. Los `.mtl` se buscan junto al OBJ; si falta uno
+    /// se avisa y sus caras se dibujan sin material.
+    pub fn load(path: &str) -> Result<Self, ObjError> {
+        let text = fs::read_to_string(path).map_err(|source| ObjError::Io { path: path.to_string(), source })?;
+        Self::parse(&text, path)
+    }
+
+    /// Como `load`, pero sobre el texto ya leído; `path` solo aparece en los errores.
+    pub fn parse(text: &str, path: &str) -> Result<Self, ObjError> {
+        let mut positions: Vec<Vec3> = Vec::new();
+        let mut file_normals: Vec<Vec3> = Vec::new();
+        let mut file_uvs: Vec<Vec2> = Vec::new();
+
+        let mut corners: HashMap<(usize, Option<usize>, Option<usize>), usize> = HashMap::new();
+        let mut vertices = Vec::new();
+        let mut normals = Vec::new();
+        let mut uvs = Vec::new();
+        let mut all_normals = true;
+        let mut all_uvs = true;
+        let mut indices = Vec::new();
+
+        let dir = Path::new(path).parent().unwrap_or(Path::new(""));
+        let mut materials: Vec<Material> = Vec::new();
+        let mut libraries_ok = true;
+        let mut dependencies = Vec::new();
+        let mut ranges: Vec<MaterialRange> = Vec::new();
+        let mut current: Option<usize> = None;
+
+        for (n, raw) in text.lines().enumerate() {
+            let err = |kind| ObjError::Parse { path: path.to_string(), line: n + 1, kind };
+            let line = raw.split('#').next().unwrap_or("");
+            let mut parts = line.split_whitespace();
+            let Some(keyword) = parts.next() else { continue };
+
+            match keyword {
                 "v" => {
-                    let x: f32 = parts[1].parse().unwrap();
-                    let y: f32 = parts[2].parse().unwrap();
-                    let z: f32 = parts[3].parse().unwrap();
-                    vertices.push(Vec3::new(x, y, z));
+                    let [x, y, z] = parse_floats(keyword, &mut parts, 3).map_err(err)?;
+                    positions.push(Vec3::new(x, y, z));
+                }
+                "vn" => {
+                    let [x, y, z] = parse_floats(keyword, &mut parts, 3).map_err(err)?;
+                    file_normals.push(Vec3::new(x, y, z).normalize_or_zero());
+                }
+                "vt" => {
+                    // La V es opcional en el formato
+                    let [u, v, _] = parse_floats(keyword, &mut parts, 1).map_err(err)?;
+                    file_uvs.push(Vec2::new(u, v));
                 }
                 "f" => {
-                    let mut idx = [0usize; 3];
-                    for i in 0..3 {
-                        let v = parts[i + 1].split('/').next().unwrap();
-                        idx[i] = v.parse::<usize>().unwrap() - 1;
+                    let refs: Vec<&str> = parts.collect();
+                    if refs.len() < 3 {
+                        return Err(err(ObjErrorKind::TooFewVertices(refs.len())));
+                    }
+                    let mut face = Vec::with_capacity(refs.len());
+                    for r in refs {
+                        let mut fields = r.split('/');
+                        let v = resolve_index(fields.next().unwrap_or(""), positions.len()).map_err(err)?;
+                        let vt = match fields.next() {
+                            Some(s) if !s.is_empty() => Some(resolve_index(s, file_uvs.len()).map_err(err)?),
+                            _ => None,
+                        };
+                        let vn = match fields.next() {
+                            Some(s) if !s.is_empty() => Some(resolve_index(s, file_normals.len()).map_err(err)?),
+                            _ => None,
+                        };
+                        let index = *corners.entry((v, vt, vn)).or_insert_with(|| {
+                            vertices.push(positions[v]);
+                            normals.push(vn.map_or(Vec3::ZERO, |i| file_normals[i]));
+                            uvs.push(vt.map_or(Vec2::ZERO, |i| file_uvs[i]));
+                            all_normals &= vn.is_some();
+                            all_uvs &= vt.is_some();
+                            vertices.len() - 1
+                        });
+                        face.push(index);
+                    }
+                    let start = indices.len();
+                    for i in 1..face.len() - 1 {
+                        indices.push([face[0], face[i], face[i + 1]]);
+                    }
+                    match ranges.last_mut() {
+                        Some(r) if r.material == current => r.triangles.end = indices.len(),
+                        _ => ranges.push(MaterialRange { material: current, triangles: start..indices.len() }),
+                    }
+                }
+                "mtllib" => {
+                    for file in parts {
+                        let mtl_path = dir.join(file);
+                        dependencies.push(mtl_path.clone());
+                        match load_mtl(&mtl_path.to_string_lossy()) {
+                            Ok(found) => materials.extend(found),
+                            Err(e @ ObjError::Io { .. }) => {
+                                eprintln!("{}:{}: aviso: {}", path, n + 1, e);
+                                libraries_ok = false;
+                            }
+                            Err(e) => return Err(e),
+                        }
+                    }
+                }
+                "usemtl" => {
+                    let name = parts.next().unwrap_or("");
+                    current = materials.iter().position(|m| m.name == name);
+                    if current.is_none() && libraries_ok {
+                        eprintln!("{}:{}: aviso: material '{}' no definido", path, n + 1, name);
                     }
-                    indices.push(idx);
                 }
                 _ => {}
             }
         }
 
-        // Remove invalid faces (indices that reference missing vertices).
-        let before = indices.len();
-        indices.retain(|tri| tri[0] < vertices.len() && tri[1] < vertices.len() && tri[2] < vertices.len());
-        let removed = before - indices.len();
-        if removed > 0 {
-            eprintln!("ObjMesh load('{}'): removed {} invalid faces (indices out of range)", path, removed);
+        if !all_normals {
+            normals.clear();
+        }
+        if !all_uvs {
+            uvs.clear();
+        }
+        if ranges.iter().all(|r| r.material.is_none()) {
+            ranges.clear();
         }
+        let mut mesh = Self::new(vertices, indices);
+        mesh.normals = normals;
+        mesh.uvs = uvs;
+        dependencies.extend(materials.iter().filter_map(|m| m.diffuse_file.clone()));
+        mesh.materials = materials;
+        mesh.ranges = ranges;
+        mesh.dependencies = dependencies;
+        Ok(mesh)
+    }
+}
 
-        Self { vertices, indices }
+/// Carga un modelo según su extensión: `.gltf`/`.glb` como glTF 2.0 y el
+/// resto como OBJ.
+pub fn load_model(path: &str) -> Result<ObjMesh, Box<dyn std::error::Error>> {
+    let ext = Path::new(path).extension().and_then(|e| e.to_str()).unwrap_or("").to_ascii_lowercase();
+    match ext.as_str() {
+        "gltf" | "glb" => Ok(load_gltf(path)?),
+        _ => Ok(ObjMesh::load(path)?),
     }
 }
 
+/// Error al cargar un `.obj` o un `.mtl`.
+#[derive(Debug)]
+pub enum ObjError {
+    Io { path: String, source: std::io::Error },
+    Parse { path: String, line: usize, kind: ObjErrorKind },
+}
+
+#[derive(Debug, PartialEq)]
+pub enum ObjErrorKind {
+    InvalidNumber(String),
+    MissingValues { keyword: String, expected: usize },
+    TooFewVertices(usize),
+    InvalidIndex(String),
+    IndexOutOfRange { index: i64, count: usize },
+}
+
+impl fmt::Display for ObjError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ObjError::Io { path, source } => write!(f, "No se pudo leer {}: {}", path, source),
+            ObjError::Parse { path, line, kind } => write!(f, "{}:{}: {}", path, line, kind),
+        }
+    }
+}
+
+impl fmt::Display for ObjErrorKind {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ObjErrorKind::InvalidNumber(s) => write!(f, "número inválido '{}'", s),
+            ObjErrorKind::MissingValues { keyword, expected } => write!(f, "'{}' necesita {} valores", keyword, expected),
+            ObjErrorKind::TooFewVertices(n) => write!(f, "cara con {} vértices (mínimo 3)", n),
+            ObjErrorKind::InvalidIndex(s) => write!(f, "índice inválido '{}'", s),
+            ObjErrorKind::IndexOutOfRange { index, count } => write!(f, "índice {} fuera de rango (hay {})", index, count),
+        }
+    }
+}
+
+impl std::error::Error for ObjError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            ObjError::Io { source, .. } => Some(source),
+            ObjError::Parse { .. } => None,
+        }
+    }
+}
+
+/// Lee hasta 3 números; los que falten tras los `required` primeros valen 0.
+pub(crate) fn parse_floats<'a>(keyword: &str, parts: &mut impl Iterator<Item = &'a str>, required: usize) -> Result<[f32; 3], ObjErrorKind> {
+    let mut out = [0.0; 3];
+    for (i, slot) in out.iter_mut().enumerate() {
+        match parts.next() {
+            Some(s) => *slot = s.parse().map_err(|_| ObjErrorKind::InvalidNumber(s.to_string()))?,
+            None if i < required => {
+                return Err(ObjErrorKind::MissingValues { keyword: keyword.to_string(), expected: required })
+            }
+            None => break,
+        }
+    }
+    Ok(out)
+}
+
+/// Índice de OBJ (desde 1, o negativo relativo al final) a índice de `Vec`
+/// sobre los `count` elementos leídos hasta ahora.
+fn resolve_index(s: &str, count: usize) -> Result<usize, ObjErrorKind> {
+    let index: i64 = s.parse().map_err(|_| ObjErrorKind::InvalidIndex(s.to_string()))?;
+    let resolved = match index {
+        0 => return Err(ObjErrorKind::InvalidIndex(s.to_string())),
+        i if i > 0 => i - 1,
+        i => count as i64 + i,
+    };
+    if resolved < 0 || resolved >= count as i64 {
+        return Err(ObjErrorKind::IndexOutOfRange { index, count });
+    }
+    Ok(resolved as usize)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::TempDir;
+
+    /// Cada triángulo es antihorario visto desde el lado al que apuntan sus
+    /// normales de vértice, y las normales son unitarias.
+    fn assert_winding_matches_normals(mesh: &ObjMesh) {
+        assert_eq!(mesh.normals.len(), mesh.vertices.len());
+        assert_eq!(mesh.uvs.len(), mesh.vertices.len());
+        assert!(!mesh.indices.is_empty());
+        for n in &mesh.normals {
+            assert!((n.length() - 1.0).abs() < 1e-4);
+        }
+        for tri in &mesh.indices {
+            let [p0, p1, p2] = tri.map(|i| mesh.vertices[i]);
+            let face = (p1 - p0).cross(p2 - p0);
+            for &i in tri {
+                assert!(face.dot(mesh.normals[i]) > 0.0, "triángulo {:?} al revés", tri);
+            }
+        }
+    }
+
+    #[test]
+    fn generated_meshes_face_outwards() {
+        assert_winding_matches_normals(&ObjMesh::uv_sphere(24, 12, 1.5));
+        assert_winding_matches_normals(&ObjMesh::icosphere(2, 1.0));
+        assert_winding_matches_normals(&ObjMesh::torus(2.0, 0.5, 24, 12));
+        assert_winding_matches_normals(&ObjMesh::annulus(1.0, 2.0, 32));
+        assert_winding_matches_normals(&ObjMesh::disk(1.0, 32));
+        assert_winding_matches_normals(&ObjMesh::cube(2.0));
+    }
+
+    #[test]
+    fn generated_sphere_has_requested_radius() {
+        let mesh = ObjMesh::uv_sphere(32, 16, 1.28);
+        assert!((mesh.bounds.radius - 1.28).abs() < 1e-3);
+        assert!(mesh.vertices.iter().all(|v| (v.length() - 1.28).abs() < 1e-4));
+    }
+
+    #[test]
+    fn obj_faces_are_fan_triangulated_with_attributes() {
+        let text = "v 0 0 0\nv 1 0 0\nv 1 1 0\nv 0 1 0\n\
+                    vt 0 0\nvt 1 0\nvt 1 1\nvt 0 1\nvn 0 0 2\n\
+                    f 1/1/1 2/2/1 3/3/1 4/4/1\n";
+        let mesh = ObjMesh::parse(text, "quad.obj").unwrap();
+        assert_eq!(mesh.indices, vec![[0, 1, 2], [0, 2, 3]]);
+        assert_eq!(mesh.normals, vec![Vec3::Z; 4]);
+        assert_eq!(mesh.uvs[2], Vec2::new(1.0, 1.0));
+    }
+
+    #[test]
+    fn obj_negative_indices_and_normal_only_corners() {
+        let text = "v 0 0 0\nv 1 0 0\nv 0 1 0\nvn 0 0 1\nf -3//-1 -2//-1 -1//-1\n";
+        let mesh = ObjMesh::parse(text, "tri.obj").unwrap();
+        assert_eq!(mesh.indices, vec![[0, 1, 2]]);
+        assert_eq!(mesh.vertices[1], Vec3::X);
+        assert_eq!(mesh.normals.len(), 3);
+        assert!(mesh.uvs.is_empty());
+    }
+
+    #[test]
+    fn obj_errors_report_line() {
+        let line_of = |text: &str| match ObjMesh::parse(text, "bad.obj") {
+            Err(ObjError::Parse { line, kind, .. }) => (line, kind),
+            other => panic!("esperaba error de formato, no {:?}", other.map(|m| m.indices)),
+        };
+        assert_eq!(line_of("v 0 0 0\nv 1 x 0\n"), (2, ObjErrorKind::InvalidNumber("x".into())));
+        assert_eq!(line_of("v 0 0 0\nv 1 0 0\n# cara\nf 1 2\n"), (4, ObjErrorKind::TooFewVertices(2)));
+        assert_eq!(line_of("v 0 0 0\nf 1 2 3\n"), (2, ObjErrorKind::IndexOutOfRange { index: 2, count: 1 }));
+        assert_eq!(line_of("v 0 0\n"), (1, ObjErrorKind::MissingValues { keyword: "v".into(), expected: 3 }));
+        assert!(matches!(ObjMesh::load("no/existe.obj"), Err(ObjError::Io { .. })));
+    }
+
+    #[test]
+    fn obj_material_ranges_follow_usemtl() {
+        let dir = TempDir::new("obj_mtl");
+        dir.write("dos.mtl", "newmtl Rojo\nKd 1 0 0\nnewmtl Azul\nKd 0 0 1\n");
+        let obj = "mtllib dos.mtl\nv 0 0 0\nv 1 0 0\nv 1 1 0\nv 0 1 0\n\
+                   f 1 2 3\nusemtl Rojo\nf 1 2 3 4\nusemtl Azul\nf 1 3 4\nf 1 2 4\n";
+        let path = dir.write("dos.obj", obj);
+
+        let mesh = ObjMesh::load(&path.to_string_lossy()).unwrap();
+        assert_eq!(mesh.materials.len(), 2);
+        assert_eq!(
+            mesh.ranges,
+            vec![
+                MaterialRange { material: None, triangles: 0..1 },
+                MaterialRange { material: Some(0), triangles: 1..3 },
+                MaterialRange { material: Some(1), triangles: 3..5 },
+            ]
+        );
+
+        // Sin el .mtl solo se avisa: la malla carga sin materiales
+        fs::remove_file(dir.join("dos.mtl")).unwrap();
+        let mesh = ObjMesh::load(&path.to_string_lossy()).unwrap();
+        assert!(mesh.materials.is_empty());
+        assert!(mesh.ranges.is_empty());
+        assert_eq!(mesh.indices.len(), 5);
+    }
+}
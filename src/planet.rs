@@ -1,15 +1,69 @@
 use glam::Vec3;
 use crate::utils::v3;
+use crate::renderer::{PlanetShaderKind, ShaderParams};
+
+/// Flat ring around a planet (XZ plane), radii in world units.
+#[derive(Copy, Clone)]
+pub struct Ring {
+    pub inner: f32,
+    pub outer: f32,
+    pub color: raylib::prelude::Color,
+}
+
+/// Moon orbiting its parent planet on a circular orbit.
+pub struct Moon {
+    pub orbit_radius: f32,
+    pub orbit_speed: f32,
+    pub scale: f32,
+    pub color: raylib::prelude::Color,
+    pub shader: PlanetShaderKind,
+    pub angle_orbit: f32,
+    /// Nivel de detalle usado en el último frame (ver `LodSet::select`)
+    pub lod: usize,
+}
+
+impl Moon {
+    pub fn new(orbit_radius: f32, orbit_speed: f32, scale: f32, color: raylib::prelude::Color) -> Self {
+        Self {
+            orbit_radius,
+            orbit_speed,
+            scale,
+            color,
+            shader: PlanetShaderKind::Ice,
+            angle_orbit: 0.0,
+            lod: 0,
+        }
+    }
+
+    pub fn update(&mut self, dt: f32) {
+        self.angle_orbit += self.orbit_speed * dt;
+    }
+
+    /// World position given the parent planet's position.
+    pub fn position(&self, parent: Vec3) -> Vec3 {
+        parent + v3(
+            self.orbit_radius * self.angle_orbit.cos(),
+            0.0,
+            self.orbit_radius * self.angle_orbit.sin(),
+        )
+    }
+}
 
 pub struct Planet {
+    pub name: String,
     pub orbit_radius: f32,
     pub orbit_speed: f32,
     pub rotation_speed: f32,
     pub scale: f32,
     pub color: raylib::prelude::Color,
-    pub shader: crate::renderer::PlanetShaderKind,
+    pub shader: PlanetShaderKind,
+    pub params: ShaderParams,
+    pub ring: Option<Ring>,
+    pub moons: Vec<Moon>,
     pub angle_orbit: f32,
     pub angle_rot: f32,
+    /// Nivel de detalle usado en el último frame (ver `LodSet::select`)
+    pub lod: usize,
     // trail of previous positions for drawing orbital path
     pub trail: Vec<Vec3>,
     pub trail_max: usize,
@@ -17,45 +71,39 @@ pub struct Planet {
 
 impl Planet {
     pub fn new(
-        _name: &str,
+        name: &str,
         orbit_radius: f32,
         orbit_speed: f32,
         rotation_speed: f32,
         scale: f32,
         color: raylib::prelude::Color,
     ) -> Self {
-        Self {
-            orbit_radius,
-            orbit_speed,
-            rotation_speed,
-            scale,
-            color,
-            shader: crate::renderer::PlanetShaderKind::Default,
-            angle_orbit: 0.0,
-            angle_rot: 0.0,
-            trail: Vec::new(),
-            trail_max: 128,
-        }
+        Self::with_shader(name, orbit_radius, orbit_speed, rotation_speed, scale, color, PlanetShaderKind::Default)
     }
 
     pub fn with_shader(
-        _name: &str,
+        name: &str,
         orbit_radius: f32,
         orbit_speed: f32,
         rotation_speed: f32,
         scale: f32,
         color: raylib::prelude::Color,
-        shader: crate::renderer::PlanetShaderKind,
+        shader: PlanetShaderKind,
     ) -> Self {
         Self {
+            name: name.to_string(),
             orbit_radius,
             orbit_speed,
             rotation_speed,
             scale,
             color,
             shader,
+            params: ShaderParams::default(),
+            ring: None,
+            moons: Vec::new(),
             angle_orbit: 0.0,
             angle_rot: 0.0,
+            lod: 0,
             trail: Vec::new(),
             trail_max: 128,
         }
@@ -64,6 +112,12 @@ impl Planet {
     pub fn update(&mut self, dt: f32) {
         self.angle_orbit += self.orbit_speed * dt;
         self.angle_rot += self.rotation_speed * dt;
+        if let Some(clouds) = self.params.clouds.as_mut() {
+            clouds.angle += clouds.speed * dt;
+        }
+        for m in self.moons.iter_mut() {
+            m.update(dt);
+        }
         // record position in trail (keep recent positions up to trail_max)
         let pos = self.position();
         self.trail.push(pos);
@@ -0,0 +1,377 @@
+use glam::{Vec2, Vec3};
+
+use crate::hdr::luminance;
+
+/// Imagen en coma flotante sobre la que trabajan los efectos.
+pub struct Frame<'a> {
+    pub width: usize,
+    pub height: usize,
+    pub color: &'a mut [Vec3],
+    /// Emisión por pixel (ver `SoftwareRenderer::emissive`)
+    pub emissive: &'a [f32],
+    pub time: f32,
+}
+
+/// Bloom: bright-pass (umbral + emisión), desenfoque a 1/`downsample` de
+/// resolución y suma sobre la imagen.
+pub struct Bloom {
+    /// Luminancia a partir de la cual un pixel aporta al bloom
+    pub threshold: f32,
+    /// Aporte extra de los pixeles emisivos aunque no pasen el umbral
+    pub emissive_boost: f32,
+    pub intensity: f32,
+    /// Radio del desenfoque en pixeles de la imagen reducida
+    pub radius: usize,
+    pub downsample: usize,
+}
+
+pub struct Vignette {
+    /// Oscurecimiento en las esquinas (0 = nada, 1 = negro)
+    pub strength: f32,
+    /// Distancia al centro (normalizada) donde empieza a oscurecer
+    pub start: f32,
+}
+
+pub struct FilmGrain {
+    pub intensity: f32,
+}
+
+pub struct ChromaticAberration {
+    /// Desplazamiento máximo de R y B en las esquinas, en pixeles
+    pub strength: f32,
+}
+
+/// En qué punto de la imagen trabaja un efecto.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum Stage {
+    /// Sobre la luz de la escena, antes de la exposición (bloom, aberración)
+    Scene,
+    /// Sobre la imagen ya expuesta y tone-mapeada (viñeta, grano), para que
+    /// no cambien la exposición automática
+    Display,
+}
+
+pub enum PostEffect {
+    Bloom(Bloom),
+    Vignette(Vignette),
+    FilmGrain(FilmGrain),
+    ChromaticAberration(ChromaticAberration),
+}
+
+impl PostEffect {
+    pub fn name(&self) -> &'static str {
+        match self {
+            PostEffect::Bloom(_) => "Bloom",
+            PostEffect::Vignette(_) => "Vignette",
+            PostEffect::FilmGrain(_) => "FilmGrain",
+            PostEffect::ChromaticAberration(_) => "ChromaticAberration",
+        }
+    }
+
+    pub fn stage(&self) -> Stage {
+        match self {
+            PostEffect::Bloom(_) | PostEffect::ChromaticAberration(_) => Stage::Scene,
+            PostEffect::Vignette(_) | PostEffect::FilmGrain(_) => Stage::Display,
+        }
+    }
+
+    fn apply(&self, frame: &mut Frame, scratch: &mut Vec<Vec3>) {
+        match self {
+            PostEffect::Bloom(p) => bloom(p, frame, scratch),
+            PostEffect::Vignette(p) => vignette(p, frame),
+            PostEffect::FilmGrain(p) => film_grain(p, frame),
+            PostEffect::ChromaticAberration(p) => chromatic_aberration(p, frame, scratch),
+        }
+    }
+}
+
+pub struct PostPass {
+    pub effect: PostEffect,
+    pub enabled: bool,
+}
+
+/// Lista ordenada de efectos; cada uno se puede activar o desactivar en
+/// tiempo de ejecución.
+pub struct PostStack {
+    pub passes: Vec<PostPass>,
+    scratch: Vec<Vec3>,
+}
+
+impl PostStack {
+    pub fn new() -> Self {
+        Self { passes: Vec::new(), scratch: Vec::new() }
+    }
+
+    /// Pila por defecto: bloom, aberración cromática, viñeta y grano, en ese orden.
+    pub fn default_stack() -> Self {
+        let mut stack = Self::new();
+        stack.push(PostEffect::Bloom(Bloom {
+            threshold: 0.8,
+            emissive_boost: 1.0,
+            intensity: 0.8,
+            radius: 4,
+            downsample: 4,
+        }), true);
+        stack.push(PostEffect::ChromaticAberration(ChromaticAberration { strength: 2.0 }), false);
+        stack.push(PostEffect::Vignette(Vignette { strength: 0.45, start: 0.45 }), true);
+        stack.push(PostEffect::FilmGrain(FilmGrain { intensity: 0.04 }), false);
+        stack
+    }
+
+    pub fn push(&mut self, effect: PostEffect, enabled: bool) {
+        self.passes.push(PostPass { effect, enabled });
+    }
+
+    /// Invierte el estado del pase `index`; devuelve el nuevo estado.
+    pub fn toggle(&mut self, index: usize) -> Option<bool> {
+        let pass = self.passes.get_mut(index)?;
+        pass.enabled = !pass.enabled;
+        Some(pass.enabled)
+    }
+
+    pub fn is_active(&self) -> bool {
+        self.passes.iter().any(|p| p.enabled)
+    }
+
+    /// Aplica, en orden, los pases activos de la etapa `stage`.
+    pub fn apply(&mut self, frame: &mut Frame, stage: Stage) {
+        for pass in &self.passes {
+            if pass.enabled && pass.effect.stage() == stage {
+                pass.effect.apply(frame, &mut self.scratch);
+            }
+        }
+    }
+}
+
+fn bloom(p: &Bloom, frame: &mut Frame, scratch: &mut Vec<Vec3>) {
+    let ds = p.downsample.max(1);
+    let bw = frame.width.div_ceil(ds);
+    let bh = frame.height.div_ceil(ds);
+
+    // Bright-pass + reducción (media de cada bloque ds x ds)
+    scratch.clear();
+    scratch.resize(bw * bh, Vec3::ZERO);
+    for y in 0..frame.height {
+        let row = (y / ds) * bw;
+        for x in 0..frame.width {
+            let i = y * frame.width + x;
+            let c = frame.color[i];
+            let l = luminance(c);
+            let mut bright = if l > p.threshold { c * ((l - p.threshold) / l) } else { Vec3::ZERO };
+            bright += c * frame.emissive[i] * p.emissive_boost;
+            scratch[row + x / ds] += bright;
+        }
+    }
+    let inv = 1.0 / (ds * ds) as f32;
+    for c in scratch.iter_mut() {
+        *c *= inv;
+    }
+
+    // Dos pasadas de caja separables ~ gaussiana
+    let mut tmp = vec![Vec3::ZERO; bw * bh];
+    for _ in 0..2 {
+        box_blur_h(scratch, &mut tmp, bw, bh, p.radius);
+        box_blur_v(&tmp, scratch, bw, bh, p.radius);
+    }
+
+    // Composición con muestreo bilineal de la imagen reducida
+    for y in 0..frame.height {
+        let fy = ((y as f32 + 0.5) / ds as f32 - 0.5).max(0.0);
+        let y0 = (fy as usize).min(bh - 1);
+        let y1 = (y0 + 1).min(bh - 1);
+        let ty = fy - y0 as f32;
+        for x in 0..frame.width {
+            let fx = ((x as f32 + 0.5) / ds as f32 - 0.5).max(0.0);
+            let x0 = (fx as usize).min(bw - 1);
+            let x1 = (x0 + 1).min(bw - 1);
+            let tx = fx - x0 as f32;
+            let top = scratch[y0 * bw + x0].lerp(scratch[y0 * bw + x1], tx);
+            let bottom = scratch[y1 * bw + x0].lerp(scratch[y1 * bw + x1], tx);
+            frame.color[y * frame.width + x] += top.lerp(bottom, ty) * p.intensity;
+        }
+    }
+}
+
+fn box_blur_h(src: &[Vec3], dst: &mut [Vec3], w: usize, h: usize, r: usize) {
+    let norm = 1.0 / (2 * r + 1) as f32;
+    for y in 0..h {
+        let row = &src[y * w..(y + 1) * w];
+        let mut acc = Vec3::ZERO;
+        // Ventana deslizante con los bordes repetidos
+        for k in 0..=2 * r {
+            acc += row[(k as isize - r as isize).clamp(0, w as isize - 1) as usize];
+        }
+        for x in 0..w {
+            dst[y * w + x] = acc * norm;
+            let out = (x as isize - r as isize).clamp(0, w as isize - 1) as usize;
+            let inn = (x + r + 1).min(w - 1);
+            acc += row[inn] - row[out];
+        }
+    }
+}
+
+fn box_blur_v(src: &[Vec3], dst: &mut [Vec3], w: usize, h: usize, r: usize) {
+    let norm = 1.0 / (2 * r + 1) as f32;
+    for x in 0..w {
+        let mut acc = Vec3::ZERO;
+        for k in 0..=2 * r {
+            acc += src[(k as isize - r as isize).clamp(0, h as isize - 1) as usize * w + x];
+        }
+        for y in 0..h {
+            dst[y * w + x] = acc * norm;
+            let out = (y as isize - r as isize).clamp(0, h as isize - 1) as usize;
+            let inn = (y + r + 1).min(h - 1);
+            acc += src[inn * w + x] - src[out * w + x];
+        }
+    }
+}
+
+fn vignette(p: &Vignette, frame: &mut Frame) {
+    let center = Vec2::new(frame.width as f32, frame.height as f32) * 0.5;
+    let max_d = center.length();
+    for y in 0..frame.height {
+        for x in 0..frame.width {
+            let d = (Vec2::new(x as f32 + 0.5, y as f32 + 0.5) - center).length() / max_d;
+            let t = ((d - p.start) / (1.0 - p.start)).clamp(0.0, 1.0);
+            let k = 1.0 - p.strength * t * t;
+            frame.color[y * frame.width + x] *= k;
+        }
+    }
+}
+
+fn film_grain(p: &FilmGrain, frame: &mut Frame) {
+    // Semilla distinta por frame para que el grano no quede fijo en pantalla
+    let seed = (frame.time * 60.0) as u32;
+    for (i, c) in frame.color.iter_mut().enumerate() {
+        let mut h = (i as u32).wrapping_mul(0x9E37_79B1) ^ seed.wrapping_mul(0x85EB_CA6B);
+        h ^= h >> 15;
+        h = h.wrapping_mul(0x2C1B_3C6D);
+        h ^= h >> 12;
+        let n = (h & 0xffff) as f32 / 65535.0 - 0.5;
+        // Más visible en los medios tonos que en el negro del espacio
+        let l = luminance(*c).min(1.0);
+        *c += Vec3::splat(n * p.intensity * (0.25 + l));
+        *c = c.max(Vec3::ZERO);
+    }
+}
+
+fn chromatic_aberration(p: &ChromaticAberration, frame: &mut Frame, scratch: &mut Vec<Vec3>) {
+    scratch.clear();
+    scratch.extend_from_slice(frame.color);
+    let w = frame.width;
+    let h = frame.height;
+    let center = Vec2::new(w as f32, h as f32) * 0.5;
+    let max_d = center.length();
+    let sample = |pos: Vec2| -> Vec3 {
+        let x = (pos.x as isize).clamp(0, w as isize - 1) as usize;
+        let y = (pos.y as isize).clamp(0, h as isize - 1) as usize;
+        scratch[y * w + x]
+    };
+    for y in 0..h {
+        for x in 0..w {
+            let pos = Vec2::new(x as f32 + 0.5, y as f32 + 0.5);
+            let off = pos - center;
+            // Desplazamiento radial, crece con la distancia al centro
+            let shift = off / max_d * p.strength * (off.length() / max_d);
+            let r = sample(pos + shift).x;
+            let b = sample(pos - shift).z;
+            let c = &mut frame.color[y * w + x];
+            c.x = r;
+            c.z = b;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Aplica un único efecto a una imagen de `w` x `h`.
+    fn run(effect: PostEffect, color: &mut [Vec3], emissive: &[f32], w: usize, h: usize, time: f32) {
+        let stage = effect.stage();
+        let mut stack = PostStack::new();
+        stack.push(effect, true);
+        stack.apply(&mut Frame { width: w, height: h, color, emissive, time }, stage);
+    }
+
+    #[test]
+    fn bloom_spreads_only_what_passes_the_threshold() {
+        let (w, h) = (16, 16);
+        let bloom = || PostEffect::Bloom(Bloom { threshold: 1.0, emissive_boost: 1.0, intensity: 1.0, radius: 1, downsample: 2 });
+        let dark = vec![0.0; w * h];
+
+        // Por debajo del umbral y sin emisión: nada cambia
+        let mut dim = vec![Vec3::splat(0.5); w * h];
+        run(bloom(), &mut dim, &dark, w, h, 0.0);
+        assert!(dim.iter().all(|c| *c == Vec3::splat(0.5)));
+
+        // Un pixel muy brillante ilumina a sus vecinos, menos cuanto más lejos
+        let mut bright = vec![Vec3::ZERO; w * h];
+        bright[8 * w + 8] = Vec3::splat(50.0);
+        run(bloom(), &mut bright, &dark, w, h, 0.0);
+        let at = |x: usize| luminance(bright[8 * w + x]);
+        assert!(at(10) > at(12) && at(12) > 0.0, "{} {}", at(10), at(12));
+        assert_eq!(at(15), 0.0);
+
+        // La emisión aporta aunque el pixel no pase el umbral
+        let mut glow = vec![Vec3::ZERO; w * h];
+        glow[8 * w + 8] = Vec3::splat(0.5);
+        let mut emissive = dark.clone();
+        emissive[8 * w + 8] = 1.0;
+        run(bloom(), &mut glow, &emissive, w, h, 0.0);
+        assert!(luminance(glow[8 * w + 9]) > 0.0);
+    }
+
+    #[test]
+    fn vignette_darkens_towards_the_corners() {
+        let (w, h) = (32, 32);
+        let mut color = vec![Vec3::ONE; w * h];
+        run(PostEffect::Vignette(Vignette { strength: 0.5, start: 0.5 }), &mut color, &vec![0.0; w * h], w, h, 0.0);
+        // Dentro de `start` no se toca; fuera oscurece hasta 1 - strength
+        assert_eq!(color[16 * w + 16], Vec3::ONE);
+        let diagonal: Vec<f32> = (0..16).map(|i| color[i * w + i].x).collect();
+        assert!(diagonal.windows(2).all(|p| p[0] <= p[1]), "{:?}", diagonal);
+        assert!(diagonal[0] < 0.7 && diagonal[0] >= 0.5);
+    }
+
+    #[test]
+    fn film_grain_is_deterministic_per_frame() {
+        let (w, h) = (64, 64);
+        let grain = || PostEffect::FilmGrain(FilmGrain { intensity: 0.1 });
+        let emissive = vec![0.0; w * h];
+        let grained = |time: f32, base: Vec3| {
+            let mut color = vec![base; w * h];
+            run(grain(), &mut color, &emissive, w, h, time);
+            color
+        };
+        let a = grained(1.0, Vec3::splat(0.5));
+        assert_eq!(a, grained(1.0, Vec3::splat(0.5)));
+        assert_ne!(a, grained(2.0, Vec3::splat(0.5)));
+        // Ruido centrado: la media apenas se mueve y el negro no pasa a negativo
+        let mean = a.iter().map(|c| c.x).sum::<f32>() / a.len() as f32;
+        assert!((mean - 0.5).abs() < 0.01, "{}", mean);
+        assert!(grained(1.0, Vec3::ZERO).iter().all(|c| c.min_element() >= 0.0));
+    }
+
+    #[test]
+    fn chromatic_aberration_clamps_at_the_edges() {
+        let (w, h) = (8, 6);
+        let original: Vec<Vec3> = (0..w * h).map(|i| Vec3::new(i as f32, 100.0 + i as f32, 200.0 + i as f32)).collect();
+        let emissive = vec![0.0; w * h];
+
+        // Sin fuerza no cambia nada
+        let mut color = original.clone();
+        run(PostEffect::ChromaticAberration(ChromaticAberration { strength: 0.0 }), &mut color, &emissive, w, h, 0.0);
+        assert_eq!(color, original);
+
+        // Un desplazamiento mucho mayor que la imagen lee del borde, sin salirse
+        run(PostEffect::ChromaticAberration(ChromaticAberration { strength: 100.0 }), &mut color, &emissive, w, h, 0.0);
+        for (c, o) in color.iter().zip(&original) {
+            assert_eq!(c.y, o.y, "el verde no se desplaza");
+            assert!(original.iter().any(|p| p.x == c.x) && original.iter().any(|p| p.z == c.z));
+        }
+        // Las esquinas toman el rojo de la esquina más cercana a su desplazamiento
+        assert_eq!(color[0].x, original[0].x);
+        assert_eq!(color[w * h - 1].x, original[w * h - 1].x);
+    }
+}
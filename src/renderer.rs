@@ -1,10 +1,15 @@
 use raylib::prelude::*;
 use glam::{Vec2, Vec3, Vec4, Mat4, Quat};
 
-use crate::camera::Camera;
+use crate::camera::{Camera, Frustum};
 use crate::skybox::Skybox;
-use crate::object::ObjMesh;
-use crate::utils::clamp01;
+use crate::object::{CullMode, MaterialRange, ObjMesh};
+use crate::material::Material;
+use crate::texture::{linear_to_srgb, linear_to_srgb_u8};
+use crate::utils::{fbm3, smoothstep};
+use crate::hdr::{AutoExposure, ToneMap};
+use crate::postfx::{Frame, PostStack, Stage};
+use crate::aa::{fxaa, AntiAliasing};
 
 /// Tipos de shader para planetas
 #[derive(Copy, Clone)]
@@ -15,9 +20,74 @@ pub enum PlanetShaderKind {
     Volcanic,
     Ice,
     Gas,
+    /// Anillos planetarios: bandas según la distancia al centro
+    Ring,
     Default,
 }
 
+/// Parámetros por cuerpo que modulan el shader, para que dos planetas del
+/// mismo tipo no se vean idénticos.
+#[derive(Copy, Clone)]
+pub struct ShaderParams {
+    /// Desplazamiento aplicado a las coordenadas del ruido
+    pub noise_offset: Vec3,
+    /// Frecuencia del ruido (1.0 = la del shader original)
+    pub noise_scale: f32,
+    /// Capa de nubes opcional sobre la superficie
+    pub clouds: Option<CloudLayer>,
+}
+
+impl Default for ShaderParams {
+    fn default() -> Self {
+        Self {
+            noise_offset: Vec3::ZERO,
+            noise_scale: 1.0,
+            clouds: None,
+        }
+    }
+}
+
+/// Capa de nubes evaluada dentro del shader, en coordenadas del objeto para
+/// que gire con su propia velocidad independiente de la superficie.
+#[derive(Copy, Clone)]
+pub struct CloudLayer {
+    /// Fracción aproximada del planeta cubierta (0..1)
+    pub coverage: f32,
+    /// Velocidad de giro alrededor de Y (rad/s)
+    pub speed: f32,
+    /// Ángulo actual; lo avanza `Planet::update`
+    pub angle: f32,
+    /// Frecuencia del ruido de nubes
+    pub scale: f32,
+    /// Semilla (desplazamiento) del ruido de nubes
+    pub seed: f32,
+    /// Oscurecimiento máximo de la sombra proyectada sobre la superficie
+    pub shadow: f32,
+    pub color: Vec3,
+}
+
+impl CloudLayer {
+    pub fn new(coverage: f32, speed: f32) -> Self {
+        Self {
+            coverage,
+            speed,
+            angle: 0.0,
+            scale: 3.0,
+            seed: 0.0,
+            shadow: 0.45,
+            color: Vec3::new(0.95, 0.95, 0.97),
+        }
+    }
+
+    /// Densidad (0..1) de nubes en la dirección `dir` (normalizada, espacio objeto).
+    fn density(&self, dir: Vec3) -> f32 {
+        let d = Quat::from_rotation_y(-self.angle) * dir;
+        let n = fbm3(d * self.scale + Vec3::splat(self.seed), 5);
+        let threshold = 1.0 - self.coverage;
+        smoothstep(threshold - 0.08, threshold + 0.12, n)
+    }
+}
+
 pub struct SoftwareRenderer {
     pub width: i32,
     pub height: i32,
@@ -25,8 +95,78 @@ pub struct SoftwareRenderer {
     pub color: Vec<u8>,
     /// Z-buffer (depth); valores pequeños = cerca
     depth: Vec<f32>,
+    /// Emisión por pixel (0 = nada) escrita por superficies que brillan por sí
+    /// mismas, como la lava. Sirve de fuente para el bloom.
+    pub emissive: Vec<f32>,
+    /// Buffer de color lineal en coma flotante (solo con HDR activo). Se
+    /// escribe en paralelo a `color` y `resolve_hdr` lo vuelca tone-mapeado.
+    hdr_color: Option<Vec<Vec3>>,
+    pub tone_map: ToneMap,
+    pub exposure: AutoExposure,
+    /// Efectos de post-proceso aplicados por `post_process`
+    pub post: PostStack,
+    /// Copia flotante del buffer LDR para post-procesar sin HDR
+    post_buffer: Vec<Vec3>,
+    /// Modo de anti-aliasing aplicado por `apply_aa`
+    pub aa: AntiAliasing,
+    aa_buffer: Vec<u8>,
+    /// Contadores del frame actual (se reinician en `clear`)
+    pub stats: FrameStats,
     /// Time for shader animations (in seconds)
     pub time: f32,
+    /// Último fondo de `draw_skybox`, reutilizable mientras la vista no gire
+    sky_cache: Option<SkyCache>,
+    /// Coronas de radio exterior 1 para `draw_ring`, una por proporción
+    /// entre radios; cada anillo escala la suya
+    ring_meshes: Vec<(f32, ObjMesh)>,
+    /// Pixeles semitransparentes del frame, pendientes de `resolve_translucent`
+    translucent: Vec<Fragment>,
+    /// Frustum de `visible` y la vista de la que sale (posición, objetivo,
+    /// fov y aspecto): se reutiliza mientras la cámara no cambie
+    frustum: Option<((Vec3, Vec3, f32, f32), Frustum)>,
+}
+
+/// Pixel de una superficie semitransparente ya sombreado, a la espera de
+/// mezclarse cuando esté dibujado todo lo opaco.
+struct Fragment {
+    idx: usize,
+    z: f32,
+    col: Vec3,
+    alpha: f32,
+}
+
+/// Fondo ya dibujado y la vista con la que se dibujó.
+struct SkyCache {
+    sky: u64,
+    forward: Vec3,
+    up: Vec3,
+    fov_y: f32,
+    aspect: f32,
+    color: Vec<u8>,
+    /// El mismo fondo en lineal y sin recortar, para el buffer HDR
+    linear: Vec<Vec3>,
+}
+
+/// Objetos dibujados y descartados por frustum culling en el frame actual.
+#[derive(Copy, Clone, Default, Debug)]
+pub struct FrameStats {
+    pub drawn: u32,
+    pub culled: u32,
+    /// Triángulos enviados al rasterizador (mallas visibles)
+    pub triangles: u32,
+}
+
+/// Cuánto multiplica la emisión el color en el buffer HDR (en LDR se recorta
+/// igual que antes).
+const EMISSIVE_HDR_GAIN: f32 = 3.0;
+
+/// Pasa un pixel a [0, 1] tal cual, sin decodificar sRGB.
+fn rgba_to_unit(rgba: [u8; 4]) -> Vec3 {
+    Vec3::new(rgba[0] as f32, rgba[1] as f32, rgba[2] as f32) / 255.0
+}
+
+fn to_u8(v: f32) -> u8 {
+    (v.clamp(0.0, 1.0) * 255.0) as u8
 }
 
 impl SoftwareRenderer {
@@ -37,10 +177,36 @@ impl SoftwareRenderer {
             height,
             color: vec![0; size * 4],
             depth: vec![1.0; size],
+            emissive: vec![0.0; size],
+            hdr_color: None,
+            tone_map: ToneMap::Aces,
+            exposure: AutoExposure::new(),
+            post: PostStack::default_stack(),
+            post_buffer: Vec::new(),
+            aa: AntiAliasing::Fxaa,
+            aa_buffer: Vec::new(),
+            stats: FrameStats::default(),
             time: 0.0,
+            sky_cache: None,
+            ring_meshes: Vec::new(),
+            translucent: Vec::new(),
+            frustum: None,
+        }
+    }
+
+    /// Activa o desactiva el buffer HDR en coma flotante.
+    pub fn set_hdr(&mut self, enabled: bool) {
+        if enabled && self.hdr_color.is_none() {
+            self.hdr_color = Some(vec![Vec3::ZERO; (self.width * self.height) as usize]);
+        } else if !enabled {
+            self.hdr_color = None;
         }
     }
 
+    pub fn hdr_enabled(&self) -> bool {
+        self.hdr_color.is_some()
+    }
+
     pub fn clear(&mut self, c: Color) {
         let size = (self.width * self.height) as usize;
         for i in 0..size {
@@ -50,6 +216,63 @@ impl SoftwareRenderer {
             self.color[idx + 2] = c.b;
             self.color[idx + 3] = c.a;
             self.depth[i] = 1.0;
+            self.emissive[i] = 0.0;
+        }
+        self.stats = FrameStats::default();
+        self.translucent.clear();
+        if let Some(hdr) = self.hdr_color.as_mut() {
+            hdr.fill(rgba_to_unit([c.r, c.g, c.b, c.a]));
+        }
+    }
+
+    /// Aplica la pila de post-proceso. Con HDR trabaja sobre el buffer flotante
+    /// (antes del tone mapping) y deja los efectos de `Stage::Display` para
+    /// `resolve_hdr`; sin HDR, aplica todos sobre una copia flotante de `color`.
+    /// Llamar después de dibujar todo y antes de `resolve_hdr`.
+    pub fn post_process(&mut self) {
+        if !self.post.is_active() {
+            return;
+        }
+        let width = self.width as usize;
+        let height = self.height as usize;
+        if let Some(hdr) = self.hdr_color.as_mut() {
+            let mut frame = Frame { width, height, color: hdr, emissive: &self.emissive, time: self.time };
+            self.post.apply(&mut frame, Stage::Scene);
+            return;
+        }
+
+        self.post_buffer.clear();
+        self.post_buffer.extend(self.color.chunks_exact(4).map(|p| rgba_to_unit([p[0], p[1], p[2], p[3]])));
+        let mut frame = Frame { width, height, color: &mut self.post_buffer, emissive: &self.emissive, time: self.time };
+        self.post.apply(&mut frame, Stage::Scene);
+        self.post.apply(&mut frame, Stage::Display);
+        for (px, c) in self.color.chunks_exact_mut(4).zip(&self.post_buffer) {
+            px[0] = to_u8(c.x);
+            px[1] = to_u8(c.y);
+            px[2] = to_u8(c.z);
+        }
+    }
+
+    /// Con HDR activo: adapta la exposición y vuelca el buffer flotante a
+    /// `color` con el operador `tone_map`, aplicando entonces los efectos de
+    /// `Stage::Display` (la viñeta y el grano no cuentan para la exposición).
+    /// Llamar antes de `blit_to`.
+    pub fn resolve_hdr(&mut self, dt: f32) {
+        let Some(hdr) = self.hdr_color.as_ref() else {
+            return;
+        };
+        self.exposure.update(hdr, dt);
+        let exposure = self.exposure.exposure;
+        let tone_map = self.tone_map;
+        self.post_buffer.clear();
+        self.post_buffer.extend(hdr.iter().map(|c| tone_map.apply(*c * exposure)));
+        let (width, height) = (self.width as usize, self.height as usize);
+        let mut frame = Frame { width, height, color: &mut self.post_buffer, emissive: &self.emissive, time: self.time };
+        self.post.apply(&mut frame, Stage::Display);
+        for (px, c) in self.color.chunks_exact_mut(4).zip(&self.post_buffer) {
+            px[0] = to_u8(c.x);
+            px[1] = to_u8(c.y);
+            px[2] = to_u8(c.z);
         }
     }
 
@@ -62,24 +285,87 @@ impl SoftwareRenderer {
         // Accept equal depths as valid to reduce thin gaps between adjacent triangles
         if z <= self.depth[idx] {
             self.depth[idx] = z;
+            self.emissive[idx] = 0.0;
             let base = idx * 4;
             self.color[base] = rgba[0];
             self.color[base + 1] = rgba[1];
             self.color[base + 2] = rgba[2];
             self.color[base + 3] = rgba[3];
+            if let Some(hdr) = self.hdr_color.as_mut() {
+                hdr[idx] = rgba_to_unit(rgba);
+            }
         }
     }
 
-    fn put_pixel_bg(&mut self, x: i32, y: i32, rgba: [u8; 4]) {
+    /// Escribe un color lineal sin recortar y su emisión. En el buffer HDR la
+    /// emisión amplifica el color para que las superficies brillantes pasen de 1.0.
+    fn put_pixel_lit(&mut self, x: i32, y: i32, z: f32, col: Vec3, emission: f32) {
         if x < 0 || y < 0 || x >= self.width || y >= self.height {
             return;
         }
         let idx = (y * self.width + x) as usize;
-        let base = idx * 4;
-        self.color[base] = rgba[0];
-        self.color[base + 1] = rgba[1];
-        self.color[base + 2] = rgba[2];
-        self.color[base + 3] = rgba[3];
+
+        if z <= self.depth[idx] {
+            self.depth[idx] = z;
+            self.emissive[idx] = emission;
+            let base = idx * 4;
+            self.color[base] = to_u8(col.x);
+            self.color[base + 1] = to_u8(col.y);
+            self.color[base + 2] = to_u8(col.z);
+            self.color[base + 3] = 255;
+            if let Some(hdr) = self.hdr_color.as_mut() {
+                hdr[idx] = col.max(Vec3::ZERO) * (1.0 + emission * EMISSIVE_HDR_GAIN);
+            }
+        }
+    }
+
+    /// Guarda un pixel semitransparente para `resolve_translucent`. Lo que
+    /// ya está tapado por algo opaco se descarta aquí mismo.
+    fn put_pixel_blend(&mut self, x: i32, y: i32, z: f32, col: Vec3, alpha: f32) {
+        if x < 0 || y < 0 || x >= self.width || y >= self.height {
+            return;
+        }
+        let idx = (y * self.width + x) as usize;
+        if z > self.depth[idx] {
+            return;
+        }
+        self.translucent.push(Fragment { idx, z, col, alpha });
+    }
+
+    /// Mezcla los pixeles semitransparentes del frame (anillos, materiales
+    /// con opacidad < 1) de atrás hacia delante, sin escribir profundidad.
+    /// Llamar después de dibujar todo lo opaco, para que lo que queda detrás
+    /// de un anillo no lo borre aunque se dibuje más tarde.
+    pub fn resolve_translucent(&mut self) {
+        let mut fragments = std::mem::take(&mut self.translucent);
+        fragments.sort_by(|a, b| b.z.total_cmp(&a.z));
+        for f in &fragments {
+            if f.z > self.depth[f.idx] {
+                continue;
+            }
+            let base = f.idx * 4;
+            for (c, v) in [f.col.x, f.col.y, f.col.z].into_iter().enumerate() {
+                let old = self.color[base + c] as f32 / 255.0;
+                self.color[base + c] = to_u8(old + (v - old) * f.alpha);
+            }
+            if let Some(hdr) = self.hdr_color.as_mut() {
+                hdr[f.idx] = hdr[f.idx].lerp(f.col.max(Vec3::ZERO), f.alpha);
+            }
+        }
+        fragments.clear();
+        self.translucent = fragments;
+    }
+
+    /// Anti-aliasing sobre el buffer final. Llamar después de `resolve_hdr`.
+    pub fn apply_aa(&mut self) {
+        match self.aa {
+            AntiAliasing::Off => {}
+            AntiAliasing::Fxaa => {
+                self.aa_buffer.resize(self.color.len(), 0);
+                fxaa(self.width as usize, self.height as usize, &self.color, &mut self.aa_buffer);
+                std::mem::swap(&mut self.color, &mut self.aa_buffer);
+            }
+        }
     }
 
     pub fn blit_to(&self, d: &mut RaylibDrawHandle) {
@@ -104,36 +390,60 @@ impl SoftwareRenderer {
         }
     }
 
+    /// Dibuja el cielo de fondo. Está en el infinito y solo depende de hacia
+    /// dónde mira la cámara: mientras únicamente se desplaza (o gira menos de
+    /// una fracción de pixel) se copia el fondo del frame anterior.
     pub fn draw_skybox(&mut self, cam: &Camera, sky: &Skybox) {
-        let w = self.width;
-        let h = self.height;
+        let w = self.width as usize;
+        let h = self.height as usize;
 
-        let fov_y = cam.fov_y.to_radians();
         let aspect = cam.aspect;
-        let tan_fov_y = (fov_y * 0.5).tan();
+        let tan_fov_y = (cam.fov_y.to_radians() * 0.5).tan();
+        let pixel_size = 2.0 * tan_fov_y / h as f32;
 
         let forward = cam.forward();
         let up_cam = cam.up();
         let right = forward.cross(up_cam).normalize();
         let up_ortho = right.cross(forward).normalize();
 
-        for y in 0..h {
-            for x in 0..w {
-                let ndc_x = ((x as f32 + 0.5) / w as f32) * 2.0 - 1.0;
-                let ndc_y = 1.0 - ((y as f32 + 0.5) / h as f32) * 2.0;
-
-                let cam_y = ndc_y * tan_fov_y;
-                let cam_x = ndc_x * tan_fov_y * aspect;
-
-                let dir = (right * cam_x + up_ortho * cam_y + forward).normalize();
-
-                let col = sky.sample(dir);
-                let r = (clamp01(col.x) * 255.0) as u8;
-                let g = (clamp01(col.y) * 255.0) as u8;
-                let b = (clamp01(col.z) * 255.0) as u8;
-
-                self.put_pixel_bg(x, y, [r, g, b, 255]);
+        // Giro admitido sin redibujar: 1/64 de pixel
+        let tolerance = pixel_size / 64.0;
+        let reusable = self.sky_cache.as_ref().is_some_and(|c| {
+            c.sky == sky.id()
+                && c.fov_y == cam.fov_y
+                && c.aspect == aspect
+                && c.forward.abs_diff_eq(forward, tolerance)
+                && c.up.abs_diff_eq(up_ortho, tolerance)
+        });
+        if !reusable {
+            let (mut color, mut linear) = self.sky_cache.take().map(|c| (c.color, c.linear)).unwrap_or_default();
+            color.resize(w * h * 4, 0);
+            linear.resize(w * h, Vec3::ZERO);
+            let lod = sky.lod(pixel_size);
+
+            // Rayo (sin normalizar) por el centro del pixel (x, y):
+            // `first + step_x * x + step_y * y`
+            let step_x = right * (2.0 * tan_fov_y * aspect / w as f32);
+            let step_y = -up_ortho * pixel_size;
+            let first = forward + right * (tan_fov_y * aspect * (1.0 / w as f32 - 1.0)) + up_ortho * (tan_fov_y * (1.0 - 1.0 / h as f32));
+
+            for (y, (row, row_linear)) in color.chunks_exact_mut(w * 4).zip(linear.chunks_exact_mut(w)).enumerate() {
+                let start = first + step_y * y as f32;
+                for (x, (px, lin)) in row.chunks_exact_mut(4).zip(row_linear).enumerate() {
+                    // El fondo va tal cual al buffer: se vuelve a codificar en sRGB
+                    let col = sky.sample(start + step_x * x as f32, lod);
+                    px.copy_from_slice(&[linear_to_srgb_u8(col.x), linear_to_srgb_u8(col.y), linear_to_srgb_u8(col.z), 255]);
+                    *lin = col;
+                }
             }
+            self.sky_cache = Some(SkyCache { sky: sky.id(), forward, up: up_ortho, fov_y: cam.fov_y, aspect, color, linear });
+        }
+
+        let cache = self.sky_cache.as_ref().expect("fondo recién dibujado");
+        self.color.copy_from_slice(&cache.color);
+        // En HDR, el valor lineal sin recortar: un cielo `.hdr` conserva lo que pasa de 1
+        if let Some(hdr) = self.hdr_color.as_mut() {
+            hdr.copy_from_slice(&cache.linear);
         }
     }
 
@@ -169,6 +479,33 @@ impl SoftwareRenderer {
         self.project_point(world, cam)
     }
 
+    /// Prueba una esfera envolvente (en mundo) contra el frustum de la cámara
+    /// y la anota en `stats`. `false` = el objeto no se ve y no hace falta dibujarlo.
+    fn visible(&mut self, cam: &Camera, center: Vec3, radius: f32) -> bool {
+        let view = (cam.pos, cam.target, cam.fov_y, cam.aspect);
+        if self.frustum.as_ref().is_none_or(|(v, _)| *v != view) {
+            self.frustum = Some((view, cam.frustum()));
+        }
+        let visible = self.frustum.as_ref().is_some_and(|(_, f)| f.intersects_sphere(center, radius));
+        if visible {
+            self.stats.drawn += 1;
+        } else {
+            self.stats.culled += 1;
+        }
+        visible
+    }
+
+    /// Radio aproximado en pixeles de una esfera de radio `radius` en `center`.
+    /// Infinito si la cámara está dentro de la esfera.
+    pub fn screen_radius(&self, center: Vec3, radius: f32, cam: &Camera) -> f32 {
+        let dist = (center - cam.pos).length();
+        if dist <= radius {
+            return f32::INFINITY;
+        }
+        let angular = (radius / dist).asin();
+        angular.tan() / (cam.fov_y.to_radians() * 0.5).tan() * self.height as f32 * 0.5
+    }
+
     fn draw_line_2d(&mut self, p0: Vec2, p1: Vec2, rgba: [u8; 4]) {
         let mut x0 = p0.x as i32;
         let mut y0 = p0.y as i32;
@@ -199,7 +536,7 @@ impl SoftwareRenderer {
 
     /// Órbita en el plano XZ
     pub fn draw_orbit(&mut self, radius: f32, cam: &Camera, color: Color) {
-        if radius <= 0.0 {
+        if radius <= 0.0 || !self.visible(cam, Vec3::ZERO, radius + 0.03) {
             return;
         }
         let segments = 256;
@@ -236,96 +573,15 @@ impl SoftwareRenderer {
         base_color: Color,
         cam: &Camera,
         shader: PlanetShaderKind,
+        params: ShaderParams,
         light_dir: Vec3,
     ) {
-        // Fast path: render Sun as a filled additive disk to avoid triangle seams
-        if matches!(shader, PlanetShaderKind::Sun) {
-            if let Some(screen) = self.project_point(pos, cam) {
-                let probe = pos + Vec3::new(scale, 0.0, 0.0);
-                let screen_radius = if let Some(p2) = self.project_point(probe, cam) {
-                    ((p2 - screen).length()).max(8.0)
-                } else {
-                    (scale * 10.0).min((self.width as f32) * 0.5)
-                };
-
-                // Cap radius to avoid huge loops when camera is extremely close
-                let max_rad = (self.width.max(self.height) as f32 * 1.5).min(2000.0);
-                let screen_radius = screen_radius.min(max_rad);
-                eprintln!("Sun fast-path screen_radius={}", screen_radius);
-                let int_rad = screen_radius.ceil() as i32;
-                let cx = screen.x.round() as i32;
-                let cy = screen.y.round() as i32;
-
-                for oy in -int_rad..=int_rad {
-                    let y = cy + oy;
-                    if y < 0 || y >= self.height { continue; }
-                    for ox in -int_rad..=int_rad {
-                        let x = cx + ox;
-                        if x < 0 || x >= self.width { continue; }
-                        let dist = ((ox * ox + oy * oy) as f32).sqrt();
-                        if dist > screen_radius { continue; }
-                        // pattern: use angle+radial bands to occasionally use slightly darker yellow
-                        let angle = (oy as f32).atan2(ox as f32);
-                        let band = ((angle * 10.0).floor() as i32 + (dist * 0.08) as i32) % 7;
-                        let add = if band == 0 {
-                            // darker patch (add less intensity)
-                            [120u8, 100u8, 40u8, 120u8]
-                        } else {
-                            // base yellow additive
-                            [200u8, 180u8, 80u8, 140u8]
-                        };
-                        self.add_blend_pixel(x, y, add);
-                    }
-                }
-            }
-            return;
-        }
-        let model = Mat4::from_scale_rotation_translation(
-            Vec3::splat(scale),
-            Quat::IDENTITY,
-            pos,
-        );
-
-        let view = cam.view_matrix();
-        let proj = cam.proj_matrix();
-        let mvp = proj * view * model;
-
-        let light_dir = light_dir.normalize();
-
-        for tri in &mesh.indices {
-            // Defensive: skip triangles with indices out of range to avoid panics
-            if tri[0] >= mesh.vertices.len() || tri[1] >= mesh.vertices.len() || tri[2] >= mesh.vertices.len() {
-                continue;
-            }
-            let v0 = mesh.vertices[tri[0]];
-            let v1 = mesh.vertices[tri[1]];
-            let v2 = mesh.vertices[tri[2]];
-
-            let w0 = model.transform_point3(v0);
-            let w1 = model.transform_point3(v1);
-            let w2 = model.transform_point3(v2);
-
-            let n = (w1 - w0).cross(w2 - w0).normalize();
-
-            if let (Some(p0), Some(p1), Some(p2)) = (
-                self.project_vertex(v0, &mvp),
-                self.project_vertex(v1, &mvp),
-                self.project_vertex(v2, &mvp),
-            ) {
-                self.raster_triangle_shaded(
-                    p0, p1, p2,
-                    w0, w1, w2,
-                    n,
-                    base_color,
-                    shader,
-                    light_dir,
-                );
-            }
-        }
+        self.draw_mesh_shaded_rot(mesh, pos, scale, 0.0, base_color, cam, shader, params, light_dir);
     }
 
-
     /// Versión que acepta una rotación alrededor del eje Y (yaw) en radianes.
+    /// Los tramos con material (`ObjMesh::ranges`) se dibujan con su material
+    /// en vez del shader.
     pub fn draw_mesh_shaded_rot(
         &mut self,
         mesh: &ObjMesh,
@@ -335,11 +591,17 @@ impl SoftwareRenderer {
         base_color: Color,
         cam: &Camera,
         shader: PlanetShaderKind,
+        params: ShaderParams,
         light_dir: Vec3,
     ) {
+        let rot = Quat::from_rotation_y(yaw);
+        if !self.visible(cam, pos + rot * mesh.bounds.center * scale, mesh.bounds.radius * scale) {
+            return;
+        }
+        self.stats.triangles += mesh.indices.len() as u32;
         let model = Mat4::from_scale_rotation_translation(
             Vec3::splat(scale),
-            Quat::from_rotation_y(yaw),
+            rot,
             pos,
         );
 
@@ -349,37 +611,53 @@ impl SoftwareRenderer {
 
         let light_dir = light_dir.normalize();
 
-        for tri in &mesh.indices {
-            // Defensive: skip triangles with indices out of range to avoid panics
-            if tri[0] >= mesh.vertices.len() || tri[1] >= mesh.vertices.len() || tri[2] >= mesh.vertices.len() {
-                continue;
-            }
-            let v0 = mesh.vertices[tri[0]];
-            let v1 = mesh.vertices[tri[1]];
-            let v2 = mesh.vertices[tri[2]];
-
-            let w0 = model.transform_point3(v0);
-            let w1 = model.transform_point3(v1);
-            let w2 = model.transform_point3(v2);
-
-            let n = (w1 - w0).cross(w2 - w0).normalize();
-
-            if let (Some(p0), Some(p1), Some(p2)) = (
-                self.project_vertex(v0, &mvp),
-                self.project_vertex(v1, &mvp),
-                self.project_vertex(v2, &mvp),
-            ) {
-                self.raster_triangle_shaded(
-                    p0, p1, p2,
-                    w0, w1, w2,
-                    n,
-                    base_color,
-                    shader,
-                    light_dir,
-                );
+        let whole = [MaterialRange { material: None, triangles: 0..mesh.indices.len() }];
+        let ranges = if mesh.ranges.is_empty() { &whole[..] } else { &mesh.ranges[..] };
+        for range in ranges {
+            let material = range.material.and_then(|m| mesh.materials.get(m));
+            for tri in &mesh.indices[range.triangles.clone()] {
+                // Defensive: skip triangles with indices out of range to avoid panics
+                if tri[0] >= mesh.vertices.len() || tri[1] >= mesh.vertices.len() || tri[2] >= mesh.vertices.len() {
+                    continue;
+                }
+                let v0 = mesh.vertices[tri[0]];
+                let v1 = mesh.vertices[tri[1]];
+                let v2 = mesh.vertices[tri[2]];
+
+                let w0 = model.transform_point3(v0);
+                let w1 = model.transform_point3(v1);
+                let w2 = model.transform_point3(v2);
+
+                let n = (w1 - w0).cross(w2 - w0).normalize();
+
+                let (Some(p0), Some(p1), Some(p2)) = (
+                    self.project_vertex(v0, &mvp),
+                    self.project_vertex(v1, &mvp),
+                    self.project_vertex(v2, &mvp),
+                ) else {
+                    continue;
+                };
+                if let Some(material) = material {
+                    let uv = tri.map(|i| mesh.uvs.get(i).copied().unwrap_or(Vec2::ZERO));
+                    self.raster_triangle_material(p0, p1, p2, [w0, w1, w2], uv, n, material, light_dir, cam.pos, mesh.cull);
+                } else {
+                    self.raster_triangle_shaded(
+                        p0, p1, p2,
+                        w0, w1, w2,
+                        v0, v1, v2,
+                        n,
+                        base_color,
+                        shader,
+                        params,
+                        light_dir,
+                        cam.pos,
+                        mesh.cull,
+                    );
+                }
             }
         }
     }
+
     fn raster_triangle_shaded(
         &mut self,
         p0: (Vec2, f32),
@@ -388,54 +666,91 @@ impl SoftwareRenderer {
         w0: Vec3,
         w1: Vec3,
         w2: Vec3,
+        l0: Vec3,
+        l1: Vec3,
+        l2: Vec3,
         normal: Vec3,
         base_color: Color,
         shader: PlanetShaderKind,
+        params: ShaderParams,
         light_dir: Vec3,
+        eye: Vec3,
+        cull: CullMode,
     ) {
         let (v0, z0) = p0;
         let (v1, z1) = p1;
         let (v2, z2) = p2;
 
-        let min_x = v0.x.min(v1.x).min(v2.x).floor().max(0.0) as i32;
-        let max_x = v0.x.max(v1.x).max(v2.x).ceil().min((self.width - 1) as f32) as i32;
-        let min_y = v0.y.min(v1.y).min(v2.y).floor().max(0.0) as i32;
-        let max_y = v0.y.max(v1.y).max(v2.y).ceil().min((self.height - 1) as f32) as i32;
-
-        let area = edge_function(v0, v1, v2);
-        if area.abs() < 1e-6 {
-            return;
-        }
-
-        for y in min_y..=max_y {
-            for x in min_x..=max_x {
-                let p = Vec2::new(x as f32 + 0.5, y as f32 + 0.5);
-
-                let w_a = edge_function(v1, v2, p);
-                let w_b = edge_function(v2, v0, p);
-                let w_c = edge_function(v0, v1, p);
-
-                    // Use a slightly larger epsilon to avoid thin black seams caused by
-                    // floating point rounding when adjacent triangles share edges.
-                    // We accept small negative values so shared edges are filled consistently.
-                    let eps = -1e-2;
-                     if w_a >= eps && w_b >= eps && w_c >= eps {
-
-                    let w0n = w_a / area;
-                    let w1n = w_b / area;
-                    let w2n = w_c / area;
-
-                    let z = w0n * z0 + w1n * z1 + w2n * z2;
-
-                    let world_pos = w0 * w0n + w1 * w1n + w2 * w2n;
+        // Una cara trasera que se dibuja (CullMode::None) se ilumina por su lado visible
+        let back_facing = (v2.x - v0.x) * (v1.y - v0.y) - (v2.y - v0.y) * (v1.x - v0.x) < 0.0;
+        let normal = if back_facing { -normal } else { normal };
+        let lambert = 0.0_f32.max(normal.dot(-light_dir));
+        let (width, height) = (self.width, self.height);
+        // Con alfa < 255 (anillos) se mezcla con lo que hay detrás en
+        // `resolve_translucent`
+        let alpha = base_color.a as f32 / 255.0;
+
+        raster_coverage(v0, v1, v2, width, height, cull, |x, y, w0n, w1n, w2n| {
+            let z = w0n * z0 + w1n * z1 + w2n * z2;
+
+            let world_pos = w0 * w0n + w1 * w1n + w2 * w2n;
+            let local_pos = l0 * w0n + l1 * w1n + l2 * w2n;
+            let to_eye = (eye - world_pos).normalize_or_zero();
+
+            let (col, emission) = shade_planet(shader, params, base_color, world_pos, local_pos, lambert, light_dir, to_eye, self.time);
+            if alpha < 1.0 {
+                self.put_pixel_blend(x, y, z, col, alpha);
+            } else {
+                self.put_pixel_lit(x, y, z, col, emission);
+            }
+        });
+    }
 
-                    let lambert = 0.0_f32.max(normal.dot(-light_dir));
+    /// Triángulo con material de `.mtl`: Blinn-Phong con textura difusa
+    /// opcional. Los pixeles con opacidad < 1 (la del material por el alfa de
+    /// la textura) se dejan para `resolve_translucent`, que los ordena con
+    /// los de los demás objetos.
+    fn raster_triangle_material(
+        &mut self,
+        p0: (Vec2, f32),
+        p1: (Vec2, f32),
+        p2: (Vec2, f32),
+        world: [Vec3; 3],
+        uv: [Vec2; 3],
+        normal: Vec3,
+        material: &Material,
+        light_dir: Vec3,
+        eye: Vec3,
+        cull: CullMode,
+    ) {
+        let (v0, z0) = p0;
+        let (v1, z1) = p1;
+        let (v2, z2) = p2;
 
-                    let rgba = shade_planet(shader, base_color, world_pos, lambert, self.time);
-                    self.put_pixel(x, y, z, rgba);
-                }
+        let area = (v2.x - v0.x) * (v1.y - v0.y) - (v2.y - v0.y) * (v1.x - v0.x);
+        let back_facing = area < 0.0;
+        let normal = if back_facing { -normal } else { normal };
+        let (width, height) = (self.width, self.height);
+
+        // Un nivel de mip por triángulo: texels que caen en cada pixel de pantalla
+        let lod = material.diffuse_map.as_ref().map_or(0.0, |tex| {
+            let uv_area = (uv[1] - uv[0]).perp_dot(uv[2] - uv[0]).abs() * (tex.w * tex.h) as f32;
+            0.5 * (uv_area / area.abs().max(1e-6)).log2()
+        });
+
+        raster_coverage(v0, v1, v2, width, height, cull, |x, y, b0, b1, b2| {
+            let z = b0 * z0 + b1 * z1 + b2 * z2;
+            let world_pos = world[0] * b0 + world[1] * b1 + world[2] * b2;
+            let to_eye = (eye - world_pos).normalize_or_zero();
+            let tex_uv = uv[0] * b0 + uv[1] * b1 + uv[2] * b2;
+
+            let (col, emission, opacity) = shade_material(material, normal, tex_uv, lod, light_dir, to_eye);
+            if opacity < 1.0 {
+                self.put_pixel_blend(x, y, z, col, opacity);
+            } else {
+                self.put_pixel_lit(x, y, z, col, emission);
             }
-        }
+        });
     }
 
     /// Additive blend into color buffer (clamps at 255). Ignores depth.
@@ -451,45 +766,157 @@ impl SoftwareRenderer {
         self.color[base + 1] = g;
         self.color[base + 2] = b;
         self.color[base + 3] = a;
+        if let Some(hdr) = self.hdr_color.as_mut() {
+            hdr[idx] += rgba_to_unit(add);
+        }
     }
 
-    /// Draw an additive glow around a projected sun center. Uses the projected
-    /// radius computed from `scale` to approximate screen-space halo size.
-    pub fn draw_sun_glow(&mut self, center: Vec3, scale: f32, cam: &Camera) {
-        // Project center
-        if let Some(screen) = self.project_point(center, cam) {
-            // Try to compute a screen-space radius by projecting a point offset by scale
-            let probe = center + Vec3::new(scale, 0.0, 0.0);
-            let screen_radius = if let Some(p2) = self.project_point(probe, cam) {
-                ((p2 - screen).length()).max(8.0)
-            } else {
-                (scale * 10.0).min((self.width as f32) * 0.5)
-            };
+    /// Suma aditiva de un color lineal: en LDR satura a 255, en HDR no se recorta.
+    fn add_blend_hdr(&mut self, x: i32, y: i32, add: Vec3) {
+        if x < 0 || y < 0 || x >= self.width || y >= self.height { return; }
+        let idx = (y * self.width + x) as usize;
+        let base = idx * 4;
+        self.color[base] = to_u8(self.color[base] as f32 / 255.0 + add.x);
+        self.color[base + 1] = to_u8(self.color[base + 1] as f32 / 255.0 + add.y);
+        self.color[base + 2] = to_u8(self.color[base + 2] as f32 / 255.0 + add.z);
+        if let Some(hdr) = self.hdr_color.as_mut() {
+            hdr[idx] += add;
+        }
+    }
 
-            let int_rad = screen_radius.ceil() as i32;
-            let glow_col = [255u8, 200u8, 120u8, 120u8];
+    /// Centro en pantalla, radio en pixeles y profundidades (centro y punto
+    /// más cercano a la cámara) del disco de un cuerpo esférico de radio `scale`.
+    fn project_disk(&self, center: Vec3, scale: f32, cam: &Camera) -> Option<(Vec2, f32, f32, f32)> {
+        let vp = cam.proj_matrix() * cam.view_matrix();
+        let (screen, center_depth) = self.project_vertex(center, &vp)?;
+        // Radio en pantalla proyectando un punto desplazado según el eje derecho de la cámara
+        let (edge, _) = self.project_vertex(center + cam.right() * scale, &vp)?;
+        // Margen: sphere.obj llega a ~1.28 * scale, así la propia superficie
+        // del cuerpo no cuenta como algo delante de él
+        let near_depth = self
+            .project_vertex(center - cam.forward() * scale * 1.5, &vp)
+            .map_or(0.0, |(_, z)| z);
+        Some((screen, (edge - screen).length().max(4.0), center_depth, near_depth))
+    }
 
-            let cx = screen.x.round() as i32;
-            let cy = screen.y.round() as i32;
+    /// Fracción visible (0..1) del disco del sol, muestreando el z-buffer en
+    /// una rejilla sobre el disco: un punto está tapado si algo escribió una
+    /// profundidad más cercana que la superficie frontal del sol. Los puntos
+    /// fuera de pantalla cuentan como no visibles. Llamar después de dibujar
+    /// todo lo que pueda tapar al sol (planetas, lunas, nave).
+    pub fn sun_visibility(&self, center: Vec3, scale: f32, cam: &Camera) -> f32 {
+        let Some((screen, radius, _, near_depth)) = self.project_disk(center, scale, cam) else {
+            return 0.0;
+        };
+        const GRID: i32 = 9;
+        let mut total = 0;
+        let mut visible = 0;
+        for j in 0..GRID {
+            for i in 0..GRID {
+                let off = Vec2::new(i as f32, j as f32) / (GRID - 1) as f32 * 2.0 - Vec2::ONE;
+                if off.length_squared() > 1.0 {
+                    continue;
+                }
+                total += 1;
+                let p = screen + off * radius * 0.9;
+                let (x, y) = (p.x as i32, p.y as i32);
+                if x < 0 || y < 0 || x >= self.width || y >= self.height {
+                    continue;
+                }
+                if self.depth[(y * self.width + x) as usize] >= near_depth - 1e-6 {
+                    visible += 1;
+                }
+            }
+        }
+        visible as f32 / total.max(1) as f32
+    }
 
-            for oy in -int_rad..=int_rad {
-                let y = cy + oy;
-                if y < 0 || y >= self.height { continue; }
-                for ox in -int_rad..=int_rad {
-                    let x = cx + ox;
-                    if x < 0 || x >= self.width { continue; }
-                    let dist = ((ox * ox + oy * oy) as f32).sqrt();
-                    if dist > screen_radius { continue; }
-                    // falloff: 1.0 at center -> 0.0 at radius
-                    let fall = 1.0 - (dist / screen_radius);
-                    let fall = fall.powf(1.5);
-                    let add = [
-                        (glow_col[0] as f32 * fall) as u8,
-                        (glow_col[1] as f32 * fall) as u8,
-                        (glow_col[2] as f32 * fall) as u8,
-                        (glow_col[3] as f32 * fall) as u8,
-                    ];
-                    self.add_blend_pixel(x, y, add);
+    /// Corona: halo aditivo alrededor del sol. El disco lo dibuja la malla con
+    /// el shader `Sun`; aquí solo se suma luz donde el z-buffer está vacío o
+    /// detrás del centro del sol, así no pinta sobre planetas delante de él
+    /// ni sobre la propia superficie. `visibility` (ver `sun_visibility`)
+    /// atenúa el halo cuando el sol está tapado. Dibujar después de los cuerpos.
+    pub fn draw_sun_glow(&mut self, center: Vec3, scale: f32, cam: &Camera, visibility: f32) {
+        if visibility <= 0.0 {
+            return;
+        }
+        let Some((screen, screen_radius, center_depth, _)) = self.project_disk(center, scale, cam) else {
+            return;
+        };
+
+        let corona_r = (screen_radius * 3.0).min(self.width.max(self.height) as f32);
+        let int_rad = corona_r.ceil() as i32;
+        // Lineal; por encima de 1 cerca del disco (solo se nota con HDR)
+        let glow_col = Vec3::new(1.6, 1.2, 0.7);
+
+        let cx = screen.x.round() as i32;
+        let cy = screen.y.round() as i32;
+
+        for oy in -int_rad..=int_rad {
+            let y = cy + oy;
+            if y < 0 || y >= self.height { continue; }
+            for ox in -int_rad..=int_rad {
+                let x = cx + ox;
+                if x < 0 || x >= self.width { continue; }
+                let dist = ((ox * ox + oy * oy) as f32).sqrt();
+                if dist > corona_r { continue; }
+                let idx = (y * self.width + x) as usize;
+                if self.depth[idx] < center_depth { continue; }
+                // falloff: fuerte en el borde del disco -> 0 en corona_r, con rayos suaves
+                let fall = (1.0 - dist / corona_r).powf(2.2);
+                let angle = (oy as f32).atan2(ox as f32);
+                let streaks = 0.85 + 0.15 * (angle * 7.0 + self.time * 0.3).sin() * (angle * 3.0 - self.time * 0.2).cos();
+                self.add_blend_hdr(x, y, glow_col * (fall * streaks * visibility));
+            }
+        }
+    }
+
+    /// Lens flare: elementos (discos y un anillo) sobre la recta que va del
+    /// sol al centro de la pantalla, escalados por `visibility`.
+    pub fn draw_lens_flare(&mut self, center: Vec3, scale: f32, cam: &Camera, visibility: f32) {
+        if visibility <= 0.0 {
+            return;
+        }
+        let Some((sun, _, _, _)) = self.project_disk(center, scale, cam) else {
+            return;
+        };
+        let size = Vec2::new(self.width as f32, self.height as f32);
+        let mid = size * 0.5;
+        // Se desvanece cuando el sol se acerca al borde de la pantalla
+        let edge = ((sun - mid) / mid).abs().max_element();
+        let fade = visibility * (1.0 - smoothstep(0.7, 1.0, edge));
+        if fade <= 0.0 {
+            return;
+        }
+
+        // (posición sobre la recta: 0 = sol, 1 = centro, 2 = reflejo opuesto;
+        //  radio relativo a la altura de pantalla; color; es anillo)
+        let elements: [(f32, f32, Vec3, bool); 7] = [
+            (0.45, 0.035, Vec3::new(0.35, 0.25, 0.12), false),
+            (0.8, 0.02, Vec3::new(0.15, 0.3, 0.25), false),
+            (1.1, 0.06, Vec3::new(0.12, 0.14, 0.3), false),
+            (1.35, 0.012, Vec3::new(0.4, 0.35, 0.2), false),
+            (1.6, 0.09, Vec3::new(0.18, 0.1, 0.05), true),
+            (1.85, 0.03, Vec3::new(0.25, 0.12, 0.3), false),
+            (2.2, 0.14, Vec3::new(0.06, 0.1, 0.14), true),
+        ];
+        let axis = mid - sun;
+        for (t, rel_r, col, ring) in elements {
+            let c = sun + axis * t;
+            let r = rel_r * size.y;
+            let int_r = r.ceil() as i32;
+            let (cx, cy) = (c.x.round() as i32, c.y.round() as i32);
+            for oy in -int_r..=int_r {
+                for ox in -int_r..=int_r {
+                    let d = ((ox * ox + oy * oy) as f32).sqrt() / r;
+                    if d > 1.0 { continue; }
+                    let k = if ring {
+                        // Anillo fino con borde suave
+                        1.0 - smoothstep(0.0, 0.12, (d - 0.88).abs())
+                    } else {
+                        1.0 - smoothstep(0.6, 1.0, d)
+                    };
+                    self.add_blend_hdr(cx + ox, cy + oy, col * (k * fade));
                 }
             }
         }
@@ -537,51 +964,55 @@ impl SoftwareRenderer {
 
 fn shade_planet(
     shader: PlanetShaderKind,
+    params: ShaderParams,
     base: Color,
     world_pos: Vec3,
+    local_pos: Vec3,
     lambert: f32,
+    light_dir: Vec3,
+    to_eye: Vec3,
     time: f32,
-) -> [u8; 4] {
+) -> (Vec3, f32) {
     let mut r = base.r as f32 / 255.0;
     let mut g = base.g as f32 / 255.0;
     let mut b = base.b as f32 / 255.0;
+    let mut emission = 0.0;
+
+    // Coordenadas del ruido (la latitud sigue usando world_pos)
+    let np = world_pos * params.noise_scale + params.noise_offset;
 
     match shader {
         PlanetShaderKind::Sun => {
-            // Simpler, solid yellow with small patch variation per-surface-grid
-            let d = world_pos.length();
-            let t = (d / 3.5).min(1.0);
-
-            // gentle flicker based on time
-            let flicker = (time * 2.0).sin() * 0.08 + (time * 4.3).cos() * 0.04;
-
-            // grid-based patch id to tint some triangles / patches differently
-            let grid = (world_pos * 20.0).floor();
-            let gx = grid.x as i32;
-            let gy = grid.y as i32;
-            let gz = grid.z as i32;
-            let patch_id = gx.wrapping_add(gy).wrapping_add(gz);
-            let is_patch = (patch_id.abs() % 7) == 0;
-
-            let base_bright = Vec3::new(1.0, 1.0, 0.5); // main yellow
-            let base_dark = Vec3::new(0.95, 0.92, 0.42); // slightly darker yellow for patches
-
-            let base_col = if is_patch { base_dark } else { base_bright };
-
-            // slight radial pale at edges
-            let edge_a = Vec3::new(1.0, 0.98, 0.55);
-            let col = base_col * (1.0 - t) + edge_a * t;
-
-            let limb_dark = 1.0 - t * t * 0.25;
-            let brightness = (0.8 + 0.2 * lambert) * limb_dark * (1.0 + flicker);
-
-            r = col.x * brightness;
-            g = col.y * brightness;
-            b = col.z * brightness;
+            // Superficie emisiva: no depende de `lambert`
+            let n = local_pos.normalize_or_zero();
+            let mu = n.dot(to_eye).clamp(0.0, 1.0);
+
+            // Granulación: celdas (ridged fBm) que hierven lentamente, más una
+            // capa grande que deriva para que el patrón no se repita
+            let boil = Vec3::new(time * 0.11, -time * 0.07, time * 0.05);
+            let cells = 1.0 - (2.0 * fbm3(n * 22.0 + boil + params.noise_offset, 3) - 1.0).abs();
+            let drift = Quat::from_rotation_y(time * 0.02) * n;
+            let large = fbm3(drift * 4.0 + params.noise_offset, 3);
+            let gran = 0.82 + 0.12 * cells + 0.12 * (large - 0.5);
+
+            // Oscurecimiento de limbo (ley lineal, u = 0.6) con el borde más rojizo
+            let limb = 1.0 - 0.6 * (1.0 - mu);
+            let center_col = Vec3::new(1.0, 0.97, 0.82);
+            let limb_col = Vec3::new(1.0, 0.62, 0.22);
+            let col = limb_col * (1.0 - mu) + center_col * mu;
+
+            // Tinte de la estrella (suavizado para que Color::YELLOW no anule el azul)
+            let tint = Vec3::new(r, g, b) * 0.5 + Vec3::splat(0.5);
+            let lit = col * tint * (limb * gran * 1.15);
+
+            r = lit.x;
+            g = lit.y;
+            b = lit.z;
+            emission = limb * gran;
         }
         PlanetShaderKind::Earth => {
             let lat = world_pos.y;
-            let noise = (world_pos.x * 0.7).sin() * (world_pos.z * 0.5).cos();
+            let noise = (np.x * 0.7).sin() * (np.z * 0.5).cos();
             let mix_val = lat * 0.4 + noise * 0.6;
 
             let ocean = Vec3::new(0.0, 0.2, 0.7);
@@ -607,11 +1038,41 @@ fn shade_planet(
             r *= diffuse;
             g *= diffuse;
             b *= diffuse;
+
+            // Normal suave de la esfera (la de la cara es plana y daría un brillo facetado)
+            let n = local_pos.normalize_or_zero();
+            let n_dot_l = n.dot(-light_dir);
+
+            // Brillo especular Blinn-Phong solo sobre el océano
+            if lat.abs() <= 1.0 && mix_val <= 0.0 && n_dot_l > 0.0 {
+                let half = (-light_dir + to_eye).normalize_or_zero();
+                let spec = n.dot(half).max(0.0).powf(80.0) * 0.9;
+                // Un poco más ancho y tenue para simular el oleaje
+                let sheen = n.dot(half).max(0.0).powf(12.0) * 0.08;
+                let glint = (spec + sheen) * smoothstep(0.0, 0.15, n_dot_l);
+                r += glint;
+                g += glint * 0.95;
+                b += glint * 0.85;
+            }
+
+            // Luces de ciudades en tierra firme, solo en el lado nocturno.
+            // Aparecen gradualmente al cruzar el terminador.
+            if lat.abs() <= 1.0 && mix_val > 0.0 {
+                let night = 1.0 - smoothstep(-0.15, 0.1, n_dot_l);
+                if night > 0.0 {
+                    let cities = fbm3(n * 14.0 + params.noise_offset, 3);
+                    let lights = smoothstep(0.6, 0.75, cities) * night;
+                    r += lights * 1.0;
+                    g += lights * 0.78;
+                    b += lights * 0.42;
+                    emission = lights * 0.6;
+                }
+            }
         }
         PlanetShaderKind::SuperEarth => {
             // SuperEarth: yellowish/tan Earth-like with oceans and continents
             let lat = world_pos.y;
-            let noise = (world_pos.x * 0.7).sin() * (world_pos.z * 0.5).cos();
+            let noise = (np.x * 0.7).sin() * (np.z * 0.5).cos();
             let mix_val = lat * 0.4 + noise * 0.6;
 
             let ocean = Vec3::new(0.1, 0.3, 0.6);
@@ -639,18 +1100,33 @@ fn shade_planet(
         }
         PlanetShaderKind::Volcanic => {
             // Volcanic: dark red/maroon surface
-            let noise = (world_pos.x * 2.5).sin() * (world_pos.z * 3.0).cos();
+            let noise = (np.x * 2.5).sin() * (np.z * 3.0).cos();
             let dark_red = Vec3::new(0.35, 0.08, 0.05);
             let lighter_red = Vec3::new(0.55, 0.12, 0.08);
             let t = (noise * 0.5 + 0.5).clamp(0.0, 1.0);
             let col = dark_red * (1.0 - t) + lighter_red * t;
             let diffuse = 0.3 + 0.65 * lambert;
-            r = col.x * diffuse;
-            g = col.y * diffuse;
-            b = col.z * diffuse;
+
+            // Canales de lava: ruido "ridged" (picos donde el fBm cruza 0.5),
+            // desplazado lentamente en el tiempo para que la lava fluya.
+            let flow = Vec3::new(time * 0.05, time * 0.02, -time * 0.04);
+            let n = fbm3(np * 1.6 + flow, 4);
+            let ridge = 1.0 - (2.0 * n - 1.0).abs();
+            let channel = smoothstep(0.82, 0.96, ridge);
+            // Pulso lento por zonas para que no brille todo a la vez
+            let pulse = 0.75 + 0.25 * (time * 1.7 + fbm3(np * 0.5, 2) * 12.0).sin();
+            let lava_e = channel * pulse;
+
+            // La emisión no depende de `lambert`: también brilla en el lado nocturno
+            let lava_col = Vec3::new(1.0, 0.45, 0.08) * (1.0 - channel) + Vec3::new(1.0, 0.85, 0.3) * channel;
+            let lit = col * diffuse * (1.0 - lava_e) + lava_col * lava_e * 1.4;
+            r = lit.x;
+            g = lit.y;
+            b = lit.z;
+            emission = lava_e;
         }
         PlanetShaderKind::Ice => {
-            let noise = (world_pos.z * 2.0).sin() * (world_pos.y * 3.0).cos();
+            let noise = (np.z * 2.0).sin() * (np.y * 3.0).cos();
             let ice1 = Vec3::new(0.7, 0.9, 1.0);
             let ice2 = Vec3::new(0.4, 0.7, 0.9);
             let t = (noise * 0.5 + 0.5).clamp(0.0, 1.0);
@@ -664,8 +1140,8 @@ fn shade_planet(
         }
         PlanetShaderKind::Gas => {
             // Gas giants: add visible horizontal bands + subtle turbulence
-            let band = (world_pos.y * 6.0).sin();
-            let turb = ((world_pos.x * 8.0).sin() * (world_pos.z * 6.0).cos()) * 0.2;
+            let band = (np.y * 6.0).sin();
+            let turb = ((np.x * 8.0).sin() * (np.z * 6.0).cos()) * 0.2;
             let col1 = Vec3::new(0.95, 0.85, 0.7);
             let col2 = Vec3::new(0.7, 0.55, 0.4);
             let t = (band * 0.5 + 0.5 + turb).clamp(0.0, 1.0);
@@ -677,6 +1153,17 @@ fn shade_planet(
             g = col.y * diffuse * haze;
             b = col.z * diffuse * haze;
         }
+        PlanetShaderKind::Ring => {
+            // `local_pos` está en el plano del anillo: bandas finas por radio
+            let radial = local_pos.length() * params.noise_scale;
+            let bands = (radial * 9.0).sin() * 0.5 + 0.5;
+            let fine = fbm3(Vec3::new(radial * 14.0, 0.0, 0.0) + params.noise_offset, 3);
+            let density = (0.35 + 0.45 * bands + 0.4 * (fine - 0.5)).clamp(0.1, 1.0);
+            let diffuse = 0.35 + 0.65 * lambert;
+            r *= density * diffuse;
+            g *= density * diffuse;
+            b *= density * diffuse;
+        }
         PlanetShaderKind::Default => {
             let diffuse = 0.3 + 0.7 * lambert;
             r *= diffuse;
@@ -685,77 +1172,533 @@ fn shade_planet(
         }
     }
 
-    [
-        (r.clamp(0.0, 1.0) * 255.0) as u8,
-        (g.clamp(0.0, 1.0) * 255.0) as u8,
-        (b.clamp(0.0, 1.0) * 255.0) as u8,
-        255,
-    ]
+    // Nubes: sombra desplazada hacia la luz y luego la propia capa encima
+    if let Some(clouds) = params.clouds {
+        let dir = local_pos.normalize_or_zero();
+        let shadow_dir = (dir - light_dir * 0.06).normalize_or_zero();
+        let shade = 1.0 - clouds.shadow * clouds.density(shadow_dir) * lambert.min(1.0);
+        r *= shade;
+        g *= shade;
+        b *= shade;
+
+        let density = clouds.density(dir);
+        emission *= 1.0 - density;
+        let cloud_col = clouds.color * (0.08 + 0.92 * lambert);
+        r = r * (1.0 - density) + cloud_col.x * density;
+        g = g * (1.0 - density) + cloud_col.y * density;
+        b = b * (1.0 - density) + cloud_col.z * density;
+    }
+
+    // Sin recortar: `put_pixel_lit` satura en LDR y conserva el rango en HDR
+    (Vec3::new(r, g, b), emission)
+}
+
+/// Blinn-Phong de un material `.mtl`: ambiente + difuso (por la textura si
+/// la hay) + especular + emisión. Devuelve el color (codificado como el
+/// framebuffer), la emisión y la opacidad: `d` por el alfa de la textura.
+fn shade_material(m: &Material, normal: Vec3, uv: Vec2, lod: f32, light_dir: Vec3, to_eye: Vec3) -> (Vec3, f32, f32) {
+    let mut kd = m.diffuse;
+    let mut opacity = m.opacity;
+    if let Some(tex) = &m.diffuse_map {
+        // V de OBJ crece hacia arriba; las filas de la imagen, hacia abajo
+        let texel = tex.sample_rgba_lod(uv.x, 1.0 - uv.y, lod);
+        // Se filtra en lineal, pero el framebuffer guarda los colores
+        // codificados, igual que el fondo: se vuelve a codificar en sRGB
+        kd *= if tex.srgb {
+            Vec3::new(linear_to_srgb(texel.x), linear_to_srgb(texel.y), linear_to_srgb(texel.z))
+        } else {
+            texel.truncate()
+        };
+        opacity *= texel.w;
+    }
+    let lambert = normal.dot(-light_dir).max(0.0);
+    let spec = if lambert > 0.0 {
+        let half = (to_eye - light_dir).normalize_or_zero();
+        normal.dot(half).max(0.0).powf(m.shininess.max(1.0))
+    } else {
+        0.0
+    };
+    let col = kd * (0.3 + 0.7 * lambert) + m.specular * spec + m.emissive;
+    (col, m.emissive.max_element().min(1.0), opacity)
 }
 
 fn lerp(a: f32, b: f32, t: f32) -> f32 {
     a + (b - a) * t
 }
 
-fn edge_function(a: Vec2, b: Vec2, c: Vec2) -> f32 {
-    (c.x - a.x) * (b.y - a.y) - (c.y - a.y) * (b.x - a.x)
+/// Bits de sub-pixel de las coordenadas en punto fijo (1/256 de pixel)
+const SUBPIXEL_BITS: u32 = 8;
+const SUBPIXEL_ONE: i64 = 1 << SUBPIXEL_BITS;
+/// Más allá de esto (en pixeles) las funciones de arista podrían desbordar i64
+const GUARD_BAND: f32 = (1 << 22) as f32;
+
+/// Regla top-left para una arista a->b de un triángulo con área positiva (ver
+/// `raster_coverage`): el interior queda donde la función de arista crece.
+/// Arista "top": horizontal con el interior debajo. Arista "left": el interior
+/// queda a su derecha.
+fn is_top_left(dx: i64, dy: i64) -> bool {
+    (dy == 0 && dx < 0) || dy > 0
+}
+
+/// Recorre los pixeles cuyo centro cae dentro del triángulo (v0, v1, v2) en
+/// coordenadas de pantalla y llama a `f(x, y, b0, b1, b2)` con las
+/// coordenadas baricéntricas de ese centro.
+///
+/// Los vértices se ajustan a punto fijo (`SUBPIXEL_BITS`) y las funciones de
+/// arista se evalúan con enteros, así que dos triángulos que comparten arista
+/// ven exactamente los mismos valores. Con la regla top-left, un centro que
+/// cae justo sobre la arista pertenece a uno solo de ellos: cada pixel se
+/// cubre exactamente una vez, sin huecos ni solapes.
+///
+/// Las caras frontales tienen área positiva en pantalla; `cull` decide cuáles
+/// se descartan. Las que se dibujan con área negativa se recorren con v1 y v2
+/// intercambiados, pero las baricéntricas se devuelven en el orden original.
+fn raster_coverage(v0: Vec2, v1: Vec2, v2: Vec2, width: i32, height: i32, cull: CullMode, mut f: impl FnMut(i32, i32, f32, f32, f32)) {
+    for v in [v0, v1, v2] {
+        if !v.is_finite() || v.abs().max_element() > GUARD_BAND {
+            return;
+        }
+    }
+    let fixed = |v: Vec2| ((v.x * SUBPIXEL_ONE as f32).round() as i64, (v.y * SUBPIXEL_ONE as f32).round() as i64);
+    let (x0, y0) = fixed(v0);
+    let (mut x1, mut y1) = fixed(v1);
+    let (mut x2, mut y2) = fixed(v2);
+
+    let mut area = (x2 - x0) * (y1 - y0) - (y2 - y0) * (x1 - x0);
+    let front = area > 0;
+    if area == 0 || (front && cull == CullMode::Front) || (!front && cull == CullMode::Back) {
+        return;
+    }
+    if !front {
+        std::mem::swap(&mut x1, &mut x2);
+        std::mem::swap(&mut y1, &mut y2);
+        area = -area;
+    }
+    let inv_area = 1.0 / area as f32;
+
+    // Caja envolvente en pixeles (desplazamiento aritmético = floor)
+    let min_x = (x0.min(x1).min(x2) >> SUBPIXEL_BITS).max(0);
+    let max_x = (x0.max(x1).max(x2) >> SUBPIXEL_BITS).min(width as i64 - 1);
+    let min_y = (y0.min(y1).min(y2) >> SUBPIXEL_BITS).max(0);
+    let max_y = (y0.max(y1).max(y2) >> SUBPIXEL_BITS).min(height as i64 - 1);
+    if min_x > max_x || min_y > max_y {
+        return;
+    }
+
+    // Aristas en el mismo orden que antes: w0 opuesta a v0 (v1->v2), etc.
+    let edges = [(x1, y1, x2, y2), (x2, y2, x0, y0), (x0, y0, x1, y1)];
+    let half = SUBPIXEL_ONE / 2;
+    let px = min_x * SUBPIXEL_ONE + half;
+    let py = min_y * SUBPIXEL_ONE + half;
+
+    let mut row = [0i64; 3];
+    let mut step_x = [0i64; 3];
+    let mut step_y = [0i64; 3];
+    let mut bias = [0i64; 3];
+    for (i, &(ax, ay, bx, by)) in edges.iter().enumerate() {
+        let dx = bx - ax;
+        let dy = by - ay;
+        row[i] = (px - ax) * dy - (py - ay) * dx;
+        step_x[i] = dy * SUBPIXEL_ONE;
+        step_y[i] = -dx * SUBPIXEL_ONE;
+        // Sobre una arista que no es top-left, w == 0 queda fuera
+        bias[i] = if is_top_left(dx, dy) { 0 } else { -1 };
+    }
+
+    for y in min_y..=max_y {
+        let mut w = row;
+        for x in min_x..=max_x {
+            if w[0] + bias[0] >= 0 && w[1] + bias[1] >= 0 && w[2] + bias[2] >= 0 {
+                let (b0, b1, b2) = (w[0] as f32 * inv_area, w[1] as f32 * inv_area, w[2] as f32 * inv_area);
+                if front {
+                    f(x as i32, y as i32, b0, b1, b2);
+                } else {
+                    f(x as i32, y as i32, b0, b2, b1);
+                }
+            }
+            for i in 0..3 {
+                w[i] += step_x[i];
+            }
+        }
+        for i in 0..3 {
+            row[i] += step_y[i];
+        }
+    }
 }
 
+/// Segmentos de la corona de los anillos
+const RING_SEGMENTS: u32 = 128;
+
 impl SoftwareRenderer {
-    /// Dibuja un anillo plano alrededor de `center` en el plano XZ entre `inner_r` y `outer_r`.
-    /// El anillo se proyecta y escribe valores de profundidad para que respete el z-buffer.
-    pub fn draw_ring(&mut self, center: Vec3, inner_r: f32, outer_r: f32, cam: &crate::camera::Camera, color: Color) {
-        let segments = 128; // Reduced from 256 to avoid excessive computation
-        for i in 0..segments {
-            let a0 = (i as f32 / segments as f32) * std::f32::consts::TAU;
-            let a1 = ((i + 1) as f32 / segments as f32) * std::f32::consts::TAU;
-
-            let p0_inner = Vec3::new(center.x + inner_r * a0.cos(), center.y, center.z + inner_r * a0.sin());
-            let p0_outer = Vec3::new(center.x + outer_r * a0.cos(), center.y, center.z + outer_r * a0.sin());
-            let p1_inner = Vec3::new(center.x + inner_r * a1.cos(), center.y, center.z + inner_r * a1.sin());
-            let p1_outer = Vec3::new(center.x + outer_r * a1.cos(), center.y, center.z + outer_r * a1.sin());
-
-            // Build view/proj matrices once to avoid recomputation
-            let view = cam.view_matrix();
-            let proj = cam.proj_matrix();
-            let vp = proj * view;
-
-            if let (Some((s0i, z0i)), Some((s0o, z0o)), Some((s1i, z1i)), Some((s1o, z1o))) = (
-                self.project_vertex(p0_inner, &vp),
-                self.project_vertex(p0_outer, &vp),
-                self.project_vertex(p1_inner, &vp),
-                self.project_vertex(p1_outer, &vp)
-            ) {
-                // Validate screen coordinates are within reasonable bounds
-                let is_valid = |p: Vec2| -> bool {
-                    p.x.is_finite() && p.y.is_finite() && 
-                    p.x >= -1000.0 && p.x <= (self.width + 1000) as f32 &&
-                    p.y >= -1000.0 && p.y <= (self.height + 1000) as f32
-                };
+    /// Anillo como corona generada (`ObjMesh::annulus`) en el plano XZ del
+    /// planeta, visible por ambas caras y mezclado según el alfa de `color`
+    /// en `resolve_translucent`. La corona se genera una vez por proporción
+    /// entre radios y se escala.
+    pub fn draw_ring(&mut self, center: Vec3, inner_r: f32, outer_r: f32, cam: &crate::camera::Camera, color: Color, light_dir: Vec3) {
+        if outer_r <= 0.0 {
+            return;
+        }
+        let ratio = inner_r / outer_r;
+        let mut rings = std::mem::take(&mut self.ring_meshes);
+        let i = rings.iter().position(|(r, _)| *r == ratio).unwrap_or_else(|| {
+            rings.push((ratio, ObjMesh::annulus(ratio, 1.0, RING_SEGMENTS)));
+            rings.len() - 1
+        });
+        // Las bandas del shader van por radio en unidades del mundo
+        let params = ShaderParams { noise_scale: outer_r, ..ShaderParams::default() };
+        self.draw_mesh_shaded(&rings[i].1, center, outer_r, color, cam, PlanetShaderKind::Ring, params, light_dir);
+        self.ring_meshes = rings;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Cuántas veces cubre `raster_coverage` cada pixel del buffer.
+    fn coverage(tris: &[[Vec2; 3]], width: i32, height: i32, cull: CullMode) -> Vec<u32> {
+        let mut count = vec![0u32; (width * height) as usize];
+        for t in tris {
+            raster_coverage(t[0], t[1], t[2], width, height, cull, |x, y, _, _, _| {
+                count[(y * width + x) as usize] += 1;
+            });
+        }
+        count
+    }
+
+    #[test]
+    fn shared_diagonal_covers_each_pixel_once() {
+        // La diagonal pasa exactamente por los centros de pixel (x + 0.5 == y + 0.5)
+        let a = Vec2::new(0.0, 0.0);
+        let b = Vec2::new(8.0, 0.0);
+        let c = Vec2::new(8.0, 8.0);
+        let d = Vec2::new(0.0, 8.0);
+        let count = coverage(&[[a, c, b], [a, d, c]], 8, 8, CullMode::Back);
+        assert!(count.iter().all(|&n| n == 1), "{:?}", count);
+    }
+
+    #[test]
+    fn cull_mode_selects_winding() {
+        let front = [Vec2::new(0.0, 0.0), Vec2::new(0.0, 8.0), Vec2::new(8.0, 0.0)];
+        let back = [front[0], front[2], front[1]];
+        let pixels = |t: [Vec2; 3], cull: CullMode| {
+            let mut n = 0;
+            raster_coverage(t[0], t[1], t[2], 8, 8, cull, |x, y, b0, b1, b2| {
+                // Las baricéntricas siguen el orden original de los vértices
+                let p = t[0] * b0 + t[1] * b1 + t[2] * b2;
+                assert!((p - Vec2::new(x as f32 + 0.5, y as f32 + 0.5)).length() < 1e-3);
+                n += 1;
+            });
+            n
+        };
+        assert!(pixels(front, CullMode::Back) > 0);
+        assert_eq!(pixels(back, CullMode::Back), 0);
+        assert_eq!(pixels(front, CullMode::Front), 0);
+        assert_eq!(pixels(back, CullMode::None), pixels(front, CullMode::None));
+    }
+
+    #[test]
+    fn textured_material_keeps_texel_brightness() {
+        use crate::texture::{Format, TextureCPU};
+        let mut m = Material::new("gris");
+        m.diffuse = Vec3::ONE;
+        m.diffuse_map = Some(TextureCPU::new(1, 1, Format::Rgb8, vec![128, 128, 128]).unwrap());
+        // Luz de frente: difuso completo y sin especular
+        let (col, _, _) = shade_material(&m, Vec3::Z, Vec2::splat(0.5), 0.0, Vec3::NEG_Z, Vec3::Z);
+        assert!((col - Vec3::splat(128.0 / 255.0)).abs().max_element() < 1e-3, "{}", col);
+    }
+
+    #[test]
+    fn hdr_sky_keeps_values_above_one() {
+        use crate::texture::TextureCPU;
+        let sky = Skybox::panorama(TextureCPU::new_hdr(4, 2, vec![Vec3::splat(4.0); 8]).unwrap());
+        let mut r = SoftwareRenderer::new(8, 6);
+        r.set_hdr(true);
+        r.draw_skybox(&Camera::new(Vec3::ZERO, Vec3::NEG_Z), &sky);
+        let hdr = r.hdr_color.as_ref().unwrap();
+        assert!(hdr.iter().all(|c| c.abs_diff_eq(Vec3::splat(4.0), 1e-4)));
+        // En el buffer LDR se recorta
+        assert!(r.color.chunks_exact(4).all(|px| px[..3] == [254, 254, 254]));
+    }
+
+    #[test]
+    fn skybox_matches_per_pixel_rays() {
+        use crate::texture::{linear_to_srgb, Format, TextureCPU};
+        // Un degradado distinto en cada cara, para que cada rayo cuente
+        let faces = std::array::from_fn(|f| {
+            let data = (0..8 * 8).flat_map(|i| [(i * 4) as u8, (f * 40) as u8, (255 - i * 3) as u8]).collect();
+            TextureCPU::new(8, 8, Format::Rgb8, data).unwrap()
+        });
+        let sky = Skybox::cube(faces);
+        let (w, h) = (24, 16);
+        let mut r = SoftwareRenderer::new(w, h);
+        let mut cam = Camera::new(Vec3::ZERO, Vec3::new(0.6, 0.3, -1.0));
+        cam.aspect = w as f32 / h as f32;
+        r.draw_skybox(&cam, &sky);
+
+        // Referencia: un rayo normalizado por pixel, sin tabla de sRGB
+        let tan_fov_y = (cam.fov_y.to_radians() * 0.5).tan();
+        let right = cam.forward().cross(cam.up()).normalize();
+        let up = right.cross(cam.forward()).normalize();
+        let lod = sky.lod(2.0 * tan_fov_y / h as f32);
+        let mut expected = Vec::new();
+        for y in 0..h {
+            for x in 0..w {
+                let ndc_x = ((x as f32 + 0.5) / w as f32) * 2.0 - 1.0;
+                let ndc_y = 1.0 - ((y as f32 + 0.5) / h as f32) * 2.0;
+                let dir = (right * ndc_x * tan_fov_y * cam.aspect + up * ndc_y * tan_fov_y + cam.forward()).normalize();
+                let col = sky.sample(dir, lod);
+                expected.extend([col.x, col.y, col.z].map(|c| (linear_to_srgb(c) * 255.0) as u8));
+                expected.push(255);
+            }
+        }
+        assert_eq!(r.color, expected);
+    }
+
+    #[test]
+    fn skybox_cache_tolerates_subpixel_turns() {
+        let sky = Skybox::panorama(crate::texture::TextureCPU::new_hdr(4, 2, vec![Vec3::splat(0.5); 8]).unwrap());
+        let mut r = SoftwareRenderer::new(32, 24);
+        let mut cam = Camera::new(Vec3::ZERO, Vec3::NEG_Z);
+        let pixel = 2.0 * (cam.fov_y.to_radians() * 0.5).tan() / 24.0;
+        r.draw_skybox(&cam, &sky);
+        let drawn_with = |r: &SoftwareRenderer| r.sky_cache.as_ref().unwrap().forward;
+
+        // Menos de 1/64 de pixel: se copia el fondo anterior
+        cam.target = Vec3::new(pixel / 128.0, 0.0, -1.0);
+        r.draw_skybox(&cam, &sky);
+        assert_eq!(drawn_with(&r), Vec3::NEG_Z);
+
+        // Un pixel entero: se vuelve a dibujar
+        cam.target = Vec3::new(pixel, 0.0, -1.0);
+        r.draw_skybox(&cam, &sky);
+        assert_eq!(drawn_with(&r), cam.forward());
+    }
+
+    #[test]
+    fn display_effects_do_not_change_exposure() {
+        let resolve = |display: bool| {
+            let mut r = SoftwareRenderer::new(32, 32);
+            r.set_hdr(true);
+            r.clear(Color::new(128, 128, 128, 255));
+            for pass in r.post.passes.iter_mut() {
+                pass.enabled = display && pass.effect.stage() == Stage::Display;
+            }
+            r.post_process();
+            r.resolve_hdr(1.0);
+            (r.exposure.exposure, r.color)
+        };
+        let (plain, plain_color) = resolve(false);
+        let (exposure, color) = resolve(true);
+        assert_eq!(exposure, plain);
+        // La viñeta sí se ve, después del tone mapping
+        assert!(color[0] < plain_color[0]);
+    }
 
-                if is_valid(s0i) && is_valid(s0o) && is_valid(s1i) && is_valid(s1o) &&
-                   z0i.is_finite() && z0o.is_finite() && z1i.is_finite() && z1o.is_finite() {
-                    let rgba = [color.r, color.g, color.b, color.a];
-                    // Draw the ring segments
-                    self.draw_line_between_with_depth(s0i, z0i, s0o, z0o, rgba);
-                    self.draw_line_between_with_depth(s1i, z1i, s1o, z1o, rgba);
-                    self.draw_line_between_with_depth(s0o, z0o, s1o, z1o, rgba);
-                    self.draw_line_between_with_depth(s0i, z0i, s1i, z1i, rgba);
+    #[test]
+    fn sun_visibility_follows_covered_fraction() {
+        let mut r = SoftwareRenderer::new(64, 64);
+        let mut cam = Camera::new(Vec3::new(0.0, 0.0, 10.0), Vec3::ZERO);
+        cam.aspect = 1.0;
+        r.clear(Color::BLACK);
+        assert_eq!(r.sun_visibility(Vec3::ZERO, 1.0, &cam), 1.0);
+
+        // Algo más cerca que el sol tapa un cuarto, la mitad o todo el disco
+        let cover = |r: &mut SoftwareRenderer, covered: &dyn Fn(i32, i32) -> bool| {
+            r.clear(Color::BLACK);
+            for y in 0..64 {
+                for x in 0..64 {
+                    if covered(x, y) {
+                        r.depth[(y * 64 + x) as usize] = 0.0;
+                    }
                 }
             }
+            r.sun_visibility(Vec3::ZERO, 1.0, &cam)
+        };
+        let quarter = cover(&mut r, &|x, y| x < 32 && y < 32);
+        let half = cover(&mut r, &|x, _| x < 32);
+        let all = cover(&mut r, &|_, _| true);
+        assert!((quarter - 0.75).abs() < 0.1, "{}", quarter);
+        assert!((half - 0.5).abs() < 0.1, "{}", half);
+        assert_eq!(all, 0.0);
+    }
+
+    #[test]
+    fn hidden_sun_draws_no_flare() {
+        let mut cam = Camera::new(Vec3::new(0.0, 0.0, 10.0), Vec3::ZERO);
+        cam.aspect = 1.0;
+        // Fuera del campo de visión y detrás de la cámara
+        for sun in [Vec3::new(30.0, 0.0, 0.0), Vec3::new(0.0, 0.0, 20.0)] {
+            let mut r = SoftwareRenderer::new(64, 64);
+            r.clear(Color::BLACK);
+            let vis = r.sun_visibility(sun, 1.0, &cam);
+            assert_eq!(vis, 0.0, "{}", sun);
+            r.draw_sun_glow(sun, 1.0, &cam, vis);
+            r.draw_lens_flare(sun, 1.0, &cam, vis);
+            // Ni forzando la visibilidad aparece un reflejo
+            r.draw_lens_flare(sun, 1.0, &cam, 1.0);
+            assert!(r.color.chunks_exact(4).all(|px| px[..3] == [0, 0, 0]), "{}", sun);
+        }
+
+        // A la vista sí lo dibuja
+        let mut r = SoftwareRenderer::new(64, 64);
+        r.clear(Color::BLACK);
+        r.draw_lens_flare(Vec3::new(1.0, 1.0, 0.0), 1.0, &cam, 1.0);
+        assert!(r.color.chunks_exact(4).any(|px| px[..3] != [0, 0, 0]));
+    }
+
+    #[test]
+    fn frame_stats_count_drawn_and_culled() {
+        let mut r = SoftwareRenderer::new(32, 32);
+        let cam = Camera::new(Vec3::new(0.0, 0.0, 10.0), Vec3::ZERO);
+        let cube = ObjMesh::cube(1.0);
+        let draw = |r: &mut SoftwareRenderer, cam: &Camera, pos: Vec3| {
+            r.draw_mesh_shaded(&cube, pos, 1.0, Color::WHITE, cam, PlanetShaderKind::Default, ShaderParams::default(), Vec3::NEG_Z);
+        };
+        r.clear(Color::BLACK);
+        draw(&mut r, &cam, Vec3::ZERO);
+        draw(&mut r, &cam, Vec3::new(0.0, 0.0, 20.0));
+        draw(&mut r, &cam, Vec3::new(100.0, 0.0, 0.0));
+        assert_eq!((r.stats.drawn, r.stats.culled, r.stats.triangles), (1, 2, 12));
+
+        // Otra cámara en el mismo frame: el frustum se recalcula
+        let behind = Camera::new(Vec3::new(0.0, 0.0, 10.0), Vec3::new(0.0, 0.0, 20.0));
+        draw(&mut r, &behind, Vec3::new(0.0, 0.0, 20.0));
+        assert_eq!((r.stats.drawn, r.stats.culled), (2, 2));
+
+        r.clear(Color::BLACK);
+        assert_eq!((r.stats.drawn, r.stats.culled, r.stats.triangles), (0, 0, 0));
+    }
+
+    #[test]
+    fn rings_reuse_their_mesh_and_blend() {
+        let mut r = SoftwareRenderer::new(64, 64);
+        r.clear(Color::BLACK);
+        let cam = Camera::new(Vec3::new(0.0, 6.0, 0.01), Vec3::ZERO);
+        let color = Color::new(255, 255, 255, 128);
+        r.draw_ring(Vec3::ZERO, 1.0, 2.0, &cam, color, Vec3::NEG_Y);
+        assert!(r.color.chunks_exact(4).all(|px| px[..3] == [0, 0, 0]), "se mezcla al resolver");
+        r.resolve_translucent();
+
+        // Mitad de alfa sobre negro: se ve, pero a media intensidad y sin tapar lo de detrás
+        let brightest = r.color.chunks_exact(4).map(|px| px[0]).max().unwrap();
+        assert!(brightest > 0 && brightest <= 128, "{}", brightest);
+        assert!(r.depth.iter().all(|&d| d == 1.0));
+
+        // Misma proporción, otro tamaño: la misma corona
+        r.draw_ring(Vec3::X, 2.0, 4.0, &cam, color, Vec3::NEG_Y);
+        assert_eq!(r.ring_meshes.len(), 1);
+    }
+
+    #[test]
+    fn ring_stays_over_opaque_drawn_after_it() {
+        let mut cam = Camera::new(Vec3::new(0.0, 6.0, 0.01), Vec3::ZERO);
+        cam.aspect = 1.0;
+        // Más lejos que el anillo y cubriéndolo entero en pantalla
+        let sphere = ObjMesh::icosphere(3, 1.0);
+        let draw_sphere = |r: &mut SoftwareRenderer| {
+            r.draw_mesh_shaded(&sphere, Vec3::new(0.0, -5.0, 0.0), 4.0, Color::BLUE, &cam, PlanetShaderKind::Default, ShaderParams::default(), Vec3::NEG_Y);
+        };
+        let mut alone = SoftwareRenderer::new(64, 64);
+        alone.clear(Color::BLACK);
+        draw_sphere(&mut alone);
+
+        // El anillo primero y la esfera, más lejos, después: el anillo sigue encima
+        let mut r = SoftwareRenderer::new(64, 64);
+        r.clear(Color::BLACK);
+        r.draw_ring(Vec3::ZERO, 1.0, 2.0, &cam, Color::new(255, 0, 0, 128), Vec3::NEG_Y);
+        draw_sphere(&mut r);
+        r.resolve_translucent();
+
+        assert_eq!(r.depth, alone.depth, "el anillo no escribe profundidad");
+        let reddened = r.color.chunks_exact(4).zip(alone.color.chunks_exact(4)).filter(|(a, b)| a[0] > b[0] && a[2] < b[2]).count();
+        assert!(reddened > 100, "{}", reddened);
+    }
+
+    #[test]
+    fn translucent_materials_sort_back_to_front() {
+        // En orden de malla: vidrio rojo delante, vidrio verde detrás y una
+        // pared azul opaca al fondo, todo de cara a la cámara
+        let quad = |z: f32| [Vec3::new(-1.0, -1.0, z), Vec3::new(1.0, -1.0, z), Vec3::new(1.0, 1.0, z), Vec3::new(-1.0, 1.0, z)];
+        let indices = (0..3).flat_map(|q| [[4 * q, 4 * q + 1, 4 * q + 2], [4 * q, 4 * q + 2, 4 * q + 3]]).collect();
+        let mut mesh = ObjMesh::new([quad(0.5), quad(0.0), quad(-0.5)].concat(), indices);
+        for (name, diffuse, opacity) in [("rojo", Vec3::X, 0.5), ("verde", Vec3::Y, 0.5), ("azul", Vec3::Z, 1.0)] {
+            let mut m = Material::new(name);
+            m.diffuse = diffuse;
+            m.opacity = opacity;
+            mesh.materials.push(m);
         }
+        mesh.ranges = (0..3).map(|q| MaterialRange { material: Some(q), triangles: 2 * q..2 * q + 2 }).collect();
+
+        let mut r = SoftwareRenderer::new(32, 32);
+        r.clear(Color::BLACK);
+        let mut cam = Camera::new(Vec3::new(0.0, 0.0, 4.0), Vec3::ZERO);
+        cam.aspect = 1.0;
+        r.draw_mesh_shaded(&mesh, Vec3::ZERO, 1.0, Color::WHITE, &cam, PlanetShaderKind::Default, ShaderParams::default(), Vec3::NEG_Z);
+        r.resolve_translucent();
+
+        // Azul, luego verde a la mitad y por último rojo a la mitad
+        let px = &r.color[(16 * 32 + 16) * 4..][..3];
+        assert!((px[0] as i32 - 128).abs() <= 1 && (px[1] as i32 - 64).abs() <= 1 && (px[2] as i32 - 64).abs() <= 1, "{:?}", px);
+    }
+
+    #[test]
+    fn sphere_mesh_is_watertight() {
+        assert_watertight(&ObjMesh::load("assets/models/sphere.obj").unwrap());
+    }
+
+    #[test]
+    fn icosphere_is_watertight() {
+        assert_watertight(&ObjMesh::icosphere(3, 1.0));
     }
 
-    fn draw_line_between_with_depth(&mut self, a: Vec2, za: f32, b: Vec2, zb: f32, rgba: [u8;4]) {
-        let dx = (b.x - a.x).abs();
-        let dy = (b.y - a.y).abs();
-        let steps = (dx.max(dy)).max(1.0).min(500.0) as i32; // Cap steps to avoid huge loops
-        for i in 0..=steps {
-            let t = i as f32 / (steps as f32).max(1.0);
-            let x = (a.x + (b.x - a.x) * t).round() as i32;
-            let y = (a.y + (b.y - a.y) * t).round() as i32;
-            let z = za + (zb - za) * t;
-            self.put_pixel(x, y, z, rgba);
+    fn assert_watertight(mesh: &ObjMesh) {
+        let (w, h) = (160, 120);
+        let renderer = SoftwareRenderer::new(w, h);
+        let cam = Camera::new(Vec3::new(0.3, 0.7, 4.0), Vec3::ZERO);
+        let mvp = cam.proj_matrix() * cam.view_matrix();
+
+        // Se separan las caras por su orientación en el mundo y no por su giro
+        // en pantalla: con el giro invertido, CullMode::Back dejaría pasar las
+        // traseras, que también forman un disco sin huecos
+        let (mut facing, mut away) = (Vec::new(), Vec::new());
+        for t in &mesh.indices {
+            let [a, b, c] = t.map(|i| mesh.vertices[i]);
+            let Some(projected) = [a, b, c]
+                .iter()
+                .map(|&v| renderer.project_vertex(v, &mvp).map(|p| p.0))
+                .collect::<Option<Vec<_>>>()
+            else {
+                continue;
+            };
+            let tri = [projected[0], projected[1], projected[2]];
+            if (b - a).cross(c - a).dot(cam.pos - (a + b + c) / 3.0) > 0.0 {
+                facing.push(tri);
+            } else {
+                away.push(tri);
+            }
+        }
+        assert!(coverage(&away, w, h, CullMode::Back).iter().all(|&n| n == 0), "back face drawn");
+        let count = coverage(&facing, w, h, CullMode::Back);
+
+        // Caras frontales de una malla cerrada y convexa: ningún pixel dos veces...
+        assert!(count.iter().all(|&n| n <= 1), "pixel covered twice");
+        // ...y sin huecos: en cada fila los pixeles cubiertos son un tramo continuo
+        let mut covered = 0;
+        for y in 0..h {
+            let row = &count[(y * w) as usize..((y + 1) * w) as usize];
+            let first = row.iter().position(|&n| n == 1);
+            let last = row.iter().rposition(|&n| n == 1);
+            if let (Some(first), Some(last)) = (first, last) {
+                assert!(row[first..=last].iter().all(|&n| n == 1), "hole in row {}", y);
+                covered += last - first + 1;
+            }
         }
+        // El disco que subtiende la esfera vista desde su distancia
+        let radius = mesh.vertices.iter().map(|v| v.length()).fold(0.0, f32::max);
+        let tan_half = (cam.fov_y.to_radians() * 0.5).tan();
+        let r = radius / (cam.pos.length_squared() - radius * radius).sqrt() / tan_half;
+        let expected = std::f32::consts::PI * (r * h as f32 * 0.5) * (r * w as f32 * 0.5 / cam.aspect);
+        assert!((covered as f32 - expected).abs() < 0.03 * expected, "covered {} of {} pixels", covered, expected);
     }
 }
@@ -1,44 +1,183 @@
 use glam::Vec3;
-use crate::texture::TextureCPU;
+use std::f32::consts::{PI, TAU};
+use std::sync::atomic::{AtomicU64, Ordering};
+use crate::assets::Assets;
+use crate::catalog::StarCatalog;
+use crate::starfield::StarField;
+use crate::texture::{Filter, TextureCPU, Wrap};
 
+/// Orden de las caras del cubo
+pub const FACES: [&str; 6] = ["right", "left", "top", "bottom", "front", "back"];
+
+/// Fondo del cielo.
 pub struct Skybox {
-    pub nx: TextureCPU,
-    pub px: TextureCPU,
-    pub ny: TextureCPU,
-    pub py: TextureCPU,
-    pub nz: TextureCPU,
-    pub pz: TextureCPU,
+    pub kind: SkyKind,
+    /// Distinto en cada cielo creado: el renderer lo usa para saber si el
+    /// fondo que tiene guardado sigue valiendo
+    id: u64,
+}
+
+/// Un cubo de seis caras o un panorama equirectangular.
+pub enum SkyKind {
+    /// Caras en el orden de `FACES`: +X, -X, +Y, -Y, +Z, -Z
+    Cube(Box<[TextureCPU; 6]>),
+    /// Longitud en horizontal (u = 0.5 mirando a -Z), latitud en vertical
+    /// (v = 0 en el cenit)
+    Panorama(TextureCPU),
+}
+
+/// Cara del cubo que ve `dir` y coordenadas (u, v) en [0, 1] dentro de ella.
+pub fn cube_face(dir: Vec3) -> (usize, f32, f32) {
+    let abs = dir.abs();
+
+    let (face, u, v) = if abs.x >= abs.y && abs.x >= abs.z {
+        if dir.x > 0.0 { (0, -dir.z/abs.x,  dir.y/abs.x) }
+        else           { (1,  dir.z/abs.x,  dir.y/abs.x) }
+    } else if abs.y >= abs.z {
+        if dir.y > 0.0 { (2, dir.x/abs.y, -dir.z/abs.y) }
+        else           { (3, dir.x/abs.y,  dir.z/abs.y) }
+    } else {
+        if dir.z > 0.0 { (4, dir.x/abs.z,  dir.y/abs.z) }
+        else           { (5, -dir.x/abs.z, dir.y/abs.z) }
+    };
+
+    (face, 0.5 * (u + 1.0), 0.5 * (v + 1.0))
+}
+
+/// Inversa de `cube_face`: dirección (sin normalizar) de (u, v) en `face`.
+pub fn face_dir(face: usize, u: f32, v: f32) -> Vec3 {
+    let (s, t) = (2.0 * u - 1.0, 2.0 * v - 1.0);
+    match face {
+        0 => Vec3::new(1.0, t, -s),
+        1 => Vec3::new(-1.0, t, s),
+        2 => Vec3::new(s, 1.0, -t),
+        3 => Vec3::new(s, -1.0, t),
+        4 => Vec3::new(s, t, 1.0),
+        _ => Vec3::new(-s, t, -1.0),
+    }
 }
 
 impl Skybox {
-    pub fn load() -> Self {
-        Self {
-            nx: TextureCPU::from_file("assets/skybox/left.png"),
-            px: TextureCPU::from_file("assets/skybox/right.png"),
-            ny: TextureCPU::from_file("assets/skybox/bottom.png"),
-            py: TextureCPU::from_file("assets/skybox/top.png"),
-            nz: TextureCPU::from_file("assets/skybox/back.png"),
-            pz: TextureCPU::from_file("assets/skybox/front.png"),
+    /// Carpeta de las caras dentro de la raíz de assets
+    pub const DIR: &'static str = "skybox";
+    /// Panoramas que se buscan en `DIR` antes que las caras, por orden
+    pub const PANORAMAS: [&'static str; 3] = ["panorama.hdr", "panorama.png", "panorama.jpg"];
+
+    /// Carga el cielo de `skybox/` a través del gestor de assets: un
+    /// panorama si hay alguno de `PANORAMAS`, si no las seis caras. Se puede
+    /// volver a llamar tras una recarga para recoger los archivos nuevos.
+    /// Las caras se muestrean sin repetir el borde contrario, así las aristas
+    /// del cubo no dejan costura.
+    pub fn load(assets: &mut Assets) -> Result<Self, String> {
+        for name in Self::PANORAMAS {
+            let rel = format!("{}/{}", Self::DIR, name);
+            if assets.path(&rel).exists() {
+                let h = assets.texture(&rel)?;
+                return Ok(Self::panorama(assets.get_texture(h).clone()));
+            }
         }
+        let mut face = |name: &str| -> Result<TextureCPU, String> {
+            let h = assets.texture(&format!("{}/{}.png", Self::DIR, name))?;
+            Ok(assets.get_texture(h).clone())
+        };
+        let [px, nx, py, ny, pz, nz] = FACES;
+        Ok(Self::cube([face(px)?, face(nx)?, face(py)?, face(ny)?, face(pz)?, face(nz)?]))
     }
 
-    pub fn sample(&self, dir: Vec3) -> Vec3 {
-        let abs = dir.abs();
+    pub fn cube(mut faces: [TextureCPU; 6]) -> Self {
+        for tex in &mut faces {
+            tex.wrap = Wrap::Clamp;
+            tex.filter = Filter::Trilinear;
+        }
+        Self::with_kind(SkyKind::Cube(Box::new(faces)))
+    }
 
-        let (tex, u, v) = if abs.x >= abs.y && abs.x >= abs.z {
-            if dir.x > 0.0 { (&self.px, -dir.z/abs.x,  dir.y/abs.x) }
-            else           { (&self.nx,  dir.z/abs.x,  dir.y/abs.x) }
-        } else if abs.y >= abs.z {
-            if dir.y > 0.0 { (&self.py, dir.x/abs.y, -dir.z/abs.y) }
-            else           { (&self.ny, dir.x/abs.y,  dir.z/abs.y) }
-        } else {
-            if dir.z > 0.0 { (&self.pz, dir.x/abs.z,  dir.y/abs.z) }
-            else           { (&self.nz, -dir.x/abs.z, dir.y/abs.z) }
-        };
+    pub fn panorama(mut tex: TextureCPU) -> Self {
+        // Repetir une el borde derecho con el izquierdo; en los polos se
+        // recorta `v` (ver `sample`)
+        tex.wrap = Wrap::Repeat;
+        tex.filter = Filter::Trilinear;
+        Self::with_kind(SkyKind::Panorama(tex))
+    }
 
-        let u = 0.5 * (u + 1.0);
-        let v = 0.5 * (v + 1.0);
+    fn with_kind(kind: SkyKind) -> Self {
+        static NEXT_ID: AtomicU64 = AtomicU64::new(0);
+        Self { kind, id: NEXT_ID.fetch_add(1, Ordering::Relaxed) }
+    }
 
-        tex.sample(u, v)
+    pub fn id(&self) -> u64 {
+        self.id
+    }
+
+    /// Campo de estrellas y nebulosa generados a partir de una semilla, sin
+    /// imágenes.
+    pub fn procedural(stars: &StarField) -> Self {
+        Self::cube(stars.bake())
+    }
+
+    /// Estrellas reales del catálogo en su posición (ascensión recta y
+    /// declinación), con las constelaciones si `lines`.
+    pub fn catalog(catalog: &StarCatalog, lines: bool) -> Self {
+        Self::cube(catalog.bake(StarCatalog::FACE_SIZE, lines))
+    }
+
+    /// Nivel de mip para pixeles que abarcan `pixel_size` (tangente del
+    /// ángulo) en el centro de la vista.
+    pub fn lod(&self, pixel_size: f32) -> f32 {
+        match &self.kind {
+            SkyKind::Cube(faces) => (pixel_size * 0.5 * faces[0].w as f32).log2(),
+            SkyKind::Panorama(tex) => (pixel_size * tex.w as f32 / TAU).log2(),
+        }
+    }
+
+    /// Color lineal en la dirección `dir` (no hace falta normalizarla), con
+    /// el nivel de mip `lod`.
+    pub fn sample(&self, dir: Vec3, lod: f32) -> Vec3 {
+        match &self.kind {
+            SkyKind::Cube(faces) => {
+                let (face, u, v) = cube_face(dir);
+                faces[face].sample_lod(u, v, lod)
+            }
+            SkyKind::Panorama(tex) => {
+                let dir = dir.normalize_or_zero();
+                let u = 0.5 + dir.x.atan2(-dir.z) / TAU;
+                // Medio texel del nivel más grueso que se lee: con `Repeat`,
+                // el bilineal junto a un polo mezclaría la fila del contrario
+                let last = tex.w.max(tex.h).ilog2();
+                let coarsest = if lod.is_finite() { lod.ceil().clamp(0.0, last as f32) as u32 } else { 0 };
+                let half_texel = 0.5 / (tex.h >> coarsest).max(1) as f32;
+                let v = (dir.y.clamp(-1.0, 1.0).acos() / PI).clamp(half_texel, 1.0 - half_texel);
+                tex.sample_lod(u, v, lod)
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn face_dir_inverts_cube_face() {
+        for face in 0..6 {
+            for (u, v) in [(0.5, 0.5), (0.1, 0.8), (0.9, 0.25)] {
+                let (f, fu, fv) = cube_face(face_dir(face, u, v) * 3.0);
+                assert_eq!(f, face);
+                assert!((fu - u).abs() < 1e-6 && (fv - v).abs() < 1e-6);
+            }
+        }
+    }
+
+    #[test]
+    fn panorama_poles_do_not_bleed_at_coarse_mips() {
+        // Mitad de arriba roja y mitad de abajo azul
+        let texels = (0..16 * 8).map(|i| if i < 16 * 4 { Vec3::X } else { Vec3::Z }).collect();
+        let sky = Skybox::panorama(TextureCPU::new_hdr(16, 8, texels).unwrap());
+        for lod in [0.0, 1.0, 1.5, 2.0] {
+            let zenith = sky.sample(Vec3::Y, lod);
+            let nadir = sky.sample(Vec3::NEG_Y, lod);
+            assert!(zenith.abs_diff_eq(Vec3::X, 1e-5), "lod {}: {}", lod, zenith);
+            assert!(nadir.abs_diff_eq(Vec3::Z, 1e-5), "lod {}: {}", lod, nadir);
+        }
     }
 }
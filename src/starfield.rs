@@ -0,0 +1,198 @@
+use glam::Vec3;
+use std::f32::consts::TAU;
+
+use crate::skybox::{cube_face, face_dir};
+use crate::texture::{linear_to_srgb, srgb_to_linear, Filter, Format, TextureCPU, Wrap};
+use crate::utils::{fbm3, smoothstep, Rng};
+
+/// Magnitud aparente que llena un texel de blanco; las más brillantes dejan halo
+const REFERENCE_MAGNITUDE: f32 = 2.0;
+
+/// Intensidad lineal de una estrella de magnitud aparente `mag` (cada
+/// magnitud es un factor 10^0.4 ~ 2.5).
+pub(crate) fn magnitude_brightness(mag: f32) -> f32 {
+    10f32.powf(-0.4 * (mag - REFERENCE_MAGNITUDE))
+}
+
+/// Resolución de la nebulosa por cara: es suave, se interpola al tamaño final
+const NEBULA_SIZE: u32 = 128;
+
+/// Cielo procedural: estrellas puntuales con magnitud y temperatura de color
+/// aleatorias sobre una capa de nebulosa, todo a partir de `seed`.
+#[derive(Clone, Debug)]
+pub struct StarField {
+    pub seed: u64,
+    pub count: usize,
+    /// Magnitud de la estrella más brillante posible (menor = más brillante)
+    pub brightest: f32,
+    /// Magnitud de la más débil
+    pub faintest: f32,
+    /// Lado en texels de cada cara del cubo generado
+    pub face_size: u32,
+    /// Intensidad de la nebulosa; 0 la quita
+    pub nebula: f32,
+}
+
+impl StarField {
+    pub fn new(seed: u64) -> Self {
+        Self { seed, count: 20000, brightest: -1.0, faintest: 7.0, face_size: 1024, nebula: 0.15 }
+    }
+
+    /// Genera las seis caras del cubo (orden de `skybox::FACES`).
+    pub fn bake(&self) -> [TextureCPU; 6] {
+        let mut rng = Rng::new(self.seed);
+        let nebula = self.nebula_faces(&mut rng);
+
+        // Cada estrella a la cara donde cae
+        let mut stars: [Vec<(f32, f32, Vec3)>; 6] = Default::default();
+        for _ in 0..self.count {
+            let z = rng.range(-1.0, 1.0);
+            let phi = rng.range(0.0, TAU);
+            let r = (1.0 - z * z).sqrt();
+            let dir = Vec3::new(r * phi.cos(), r * phi.sin(), z);
+            let brightness = magnitude_brightness(self.magnitude(&mut rng));
+            // Más estrellas frías que calientes
+            let kelvin = (3000f32.ln() + (30000f32 / 3000.0).ln() * rng.next_f32().powi(2)).exp();
+            let (face, u, v) = cube_face(dir);
+            stars[face].push((u, v, temperature_color(kelvin) * brightness));
+        }
+
+        let n = self.face_size.max(1);
+        let center = |i: u32| (i as f32 + 0.5) / n as f32;
+        encode_faces(n, |face| {
+            let mut texels: Vec<Vec3> = (0..n * n).map(|i| nebula[face].sample(center(i % n), center(i / n))).collect();
+            for &(u, v, color) in &stars[face] {
+                splat(&mut texels, n, u, v, color);
+            }
+            texels
+        })
+    }
+
+    /// Magnitud aleatoria con la distribución del cielo real: cada magnitud
+    /// más débil hay unas cuatro veces más estrellas (N ~ 10^(0.6 m)).
+    fn magnitude(&self, rng: &mut Rng) -> f32 {
+        let lo = 10f32.powf(0.6 * self.brightest);
+        let hi = 10f32.powf(0.6 * self.faintest);
+        (lo + (hi - lo) * rng.next_f32()).log10() / 0.6
+    }
+
+    /// Nebulosa en baja resolución: ruido fractal sobre la esfera, coloreado
+    /// entre dos tonos de la semilla.
+    fn nebula_faces(&self, rng: &mut Rng) -> [TextureCPU; 6] {
+        let offset = Vec3::new(rng.range(-100.0, 100.0), rng.range(-100.0, 100.0), rng.range(-100.0, 100.0));
+        let tint_a = Vec3::new(rng.range(0.2, 1.0), rng.range(0.05, 0.4), rng.range(0.3, 1.0));
+        let tint_b = Vec3::new(rng.range(0.05, 0.4), rng.range(0.2, 0.6), rng.range(0.5, 1.0));
+        let n = NEBULA_SIZE;
+        let center = |i: u32| (i as f32 + 0.5) / n as f32;
+        std::array::from_fn(|face| {
+            let data = (0..n * n)
+                .map(|i| {
+                    let dir = face_dir(face, center(i % n), center(i / n)).normalize();
+                    let density = smoothstep(0.45, 0.8, fbm3(dir * 2.5 + offset, 5));
+                    let tint = tint_a.lerp(tint_b, fbm3(dir * 5.0 - offset, 3));
+                    tint * density * density * self.nebula
+                })
+                .collect();
+            let mut tex = TextureCPU::new_hdr(n, n, data).expect("tamaño de nebulosa");
+            tex.wrap = Wrap::Clamp;
+            tex.filter = Filter::Bilinear;
+            tex
+        })
+    }
+}
+
+/// Caras de `n`x`n` a partir del color lineal de cada texel que da `face`.
+pub(crate) fn encode_faces(n: u32, mut face: impl FnMut(usize) -> Vec<Vec3>) -> [TextureCPU; 6] {
+    std::array::from_fn(|i| {
+        let data = face(i)
+            .iter()
+            .flat_map(|c| c.to_array().map(|x| (linear_to_srgb(x) * 255.0).round() as u8))
+            .collect();
+        TextureCPU::new(n, n, Format::Rgb8, data).expect("tamaño de cara")
+    })
+}
+
+/// Suma una estrella en (u, v) repartida entre los cuatro texels vecinos (así
+/// no salta de texel en texel); si pasa de 1 el sobrante se reparte en un halo.
+pub(crate) fn splat(texels: &mut [Vec3], n: u32, u: f32, v: f32, color: Vec3) {
+    let n = n as i64;
+    let mut add = |x: i64, y: i64, c: Vec3| {
+        if (0..n).contains(&x) && (0..n).contains(&y) {
+            texels[(y * n + x) as usize] += c;
+        }
+    };
+    let x = u * n as f32 - 0.5;
+    let y = v * n as f32 - 0.5;
+    let (x0, y0) = (x.floor(), y.floor());
+    let (fx, fy) = (x - x0, y - y0);
+    let (x0, y0) = (x0 as i64, y0 as i64);
+    let core = color.min(Vec3::ONE);
+    add(x0, y0, core * (1.0 - fx) * (1.0 - fy));
+    add(x0 + 1, y0, core * fx * (1.0 - fy));
+    add(x0, y0 + 1, core * (1.0 - fx) * fy);
+    add(x0 + 1, y0 + 1, core * fx * fy);
+
+    let excess = color - core;
+    if excess.max_element() > 0.0 {
+        let (cx, cy) = (x0 + fx.round() as i64, y0 + fy.round() as i64);
+        for dy in -2..=2i64 {
+            for dx in -2..=2i64 {
+                if dx != 0 || dy != 0 {
+                    let falloff = (-((dx * dx + dy * dy) as f32) / 1.5).exp();
+                    add(cx + dx, cy + dy, excess * falloff * 0.25);
+                }
+            }
+        }
+    }
+}
+
+/// Color lineal (componente mayor = 1) de un cuerpo negro a `kelvin` grados,
+/// con la aproximación de Tanner Helland.
+pub fn temperature_color(kelvin: f32) -> Vec3 {
+    let t = kelvin.clamp(1000.0, 40000.0) / 100.0;
+    let r = if t <= 66.0 { 255.0 } else { 329.698_73 * (t - 60.0).powf(-0.133_204_76) };
+    let g = if t <= 66.0 { 99.470_8 * t.ln() - 161.119_57 } else { 288.122_16 * (t - 60.0).powf(-0.075_514_85) };
+    let b = if t >= 66.0 {
+        255.0
+    } else if t <= 19.0 {
+        0.0
+    } else {
+        138.517_73 * (t - 10.0).ln() - 305.044_8
+    };
+    let c = Vec3::new(r, g, b).clamp(Vec3::ZERO, Vec3::splat(255.0)) / 255.0;
+    let linear = Vec3::new(srgb_to_linear(c.x), srgb_to_linear(c.y), srgb_to_linear(c.z));
+    linear / linear.max_element()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn temperature_goes_from_red_to_blue() {
+        let cool = temperature_color(3000.0);
+        let hot = temperature_color(20000.0);
+        assert_eq!(cool.x, 1.0);
+        assert!(cool.z < 0.3);
+        assert_eq!(hot.z, 1.0);
+        assert!(hot.x < hot.z);
+    }
+
+    #[test]
+    fn same_seed_same_sky() {
+        let mut field = StarField::new(7);
+        field.face_size = 32;
+        field.count = 300;
+        let probe = |faces: &[TextureCPU; 6]| -> Vec<Vec3> {
+            // Centros de texel: el bilineal devuelve el texel exacto
+            let center = |i: u32| (i as f32 + 0.5) / 32.0;
+            faces.iter().flat_map(|f| (0..32 * 32).map(move |i| f.sample(center(i % 32), center(i / 32)))).collect()
+        };
+        let a = probe(&field.bake());
+        assert_eq!(a, probe(&field.bake()));
+        field.seed = 8;
+        assert_ne!(a, probe(&field.bake()));
+        // Con 300 estrellas en 6x32x32 texels alguna tiene que verse
+        assert!(a.iter().any(|c| c.max_element() > 0.05));
+    }
+}
@@ -1,47 +1,568 @@
 use raylib::prelude::*;
-use glam::Vec3;
+use glam::{Vec3, Vec4};
+use std::fs;
+use std::path::Path;
+use std::sync::OnceLock;
 
+/// Cómo se interpolan los texels al muestrear.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Default)]
+pub enum Filter {
+    /// El texel más cercano
+    Nearest,
+    /// Mezcla de los cuatro texels vecinos del nivel 0
+    #[default]
+    Bilinear,
+    /// Bilineal en los dos niveles de mip más cercanos al `lod` pedido
+    Trilinear,
+}
+
+/// Qué pasa con las coordenadas fuera de [0, 1].
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Default)]
+pub enum Wrap {
+    /// Se repite el texel del borde (sin costuras entre caras del skybox)
+    Clamp,
+    #[default]
+    Repeat,
+    /// Repetición alternando el sentido: sin saltos en los bordes
+    Mirror,
+}
+
+/// Formato de los texels en memoria.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Format {
+    /// Un canal de 8 bits (máscaras de nubes, alfa de anillos); se lee como
+    /// gris opaco
+    R8,
+    Rgb8,
+    /// Color con alfa, 8 bits por canal
+    Rgba8,
+    /// Color lineal en coma flotante, sin límite superior (cielos HDR)
+    RgbF32,
+}
+
+impl Format {
+    pub(crate) fn channels(self) -> usize {
+        match self {
+            Format::R8 => 1,
+            Format::Rgb8 | Format::RgbF32 => 3,
+            Format::Rgba8 => 4,
+        }
+    }
+}
+
+/// Texels de un nivel: bytes en los formatos de 8 bits, colores lineales en `RgbF32`.
+#[derive(Clone)]
+pub(crate) enum Texels {
+    U8(Vec<u8>),
+    F32(Vec<Vec3>),
+}
+
+/// Un nivel de la cadena de mips.
+#[derive(Clone)]
+struct Level {
+    w: u32,
+    h: u32,
+    texels: Texels,
+}
+
+#[derive(Clone)]
 pub struct TextureCPU {
     pub w: u32,
     pub h: u32,
-    pub data: Vec<u8>, // RGB
+    format: Format,
+    /// Los canales de color están en sRGB y se pasan a lineal al muestrear;
+    /// si no, son valores lineales (máscaras, normales...). El alfa siempre
+    /// es lineal.
+    pub srgb: bool,
+    pub filter: Filter,
+    pub wrap: Wrap,
+    /// Cadena de mips; el nivel 0 es la imagen completa
+    levels: Vec<Level>,
+}
+
+/// Tabla de sRGB (0..255) a lineal: decodificar es un acceso, no un `powf`.
+fn srgb_table() -> &'static [f32; 256] {
+    static TABLE: OnceLock<[f32; 256]> = OnceLock::new();
+    TABLE.get_or_init(|| std::array::from_fn(|i| srgb_to_linear(i as f32 / 255.0)))
+}
+
+pub fn srgb_to_linear(c: f32) -> f32 {
+    if c <= 0.04045 { c / 12.92 } else { ((c + 0.055) / 1.055).powf(2.4) }
+}
+
+pub fn linear_to_srgb(c: f32) -> f32 {
+    let c = c.clamp(0.0, 1.0);
+    if c <= 0.003_130_8 { c * 12.92 } else { 1.055 * c.powf(1.0 / 2.4) - 0.055 }
+}
+
+/// Umbrales de `linear_to_srgb_u8`.
+struct SrgbEncoder {
+    /// `steps[k]`: menor valor lineal que da `k + 1` (infinito si ninguno)
+    steps: [f32; 255],
+    /// Resultado para el menor f32 de cada grupo de 2^16 bits; el valor
+    /// exacto está a uno o dos pasos
+    start: Vec<u8>,
+}
+
+/// `(linear_to_srgb(c) * 255.0) as u8` sin `powf`: parte del resultado de su
+/// grupo de bits y avanza por los umbrales en los que cambia.
+pub fn linear_to_srgb_u8(c: f32) -> u8 {
+    static ENCODER: OnceLock<SrgbEncoder> = OnceLock::new();
+    let enc = ENCODER.get_or_init(|| {
+        let encode = |c: f32| (linear_to_srgb(c) * 255.0) as u8;
+        // Para cada k, el menor f32 de [0, 1] que ya da k + 1 (los f32
+        // positivos se ordenan igual que sus bits)
+        let steps: [f32; 255] = std::array::from_fn(|k| {
+            let (mut lo, mut hi) = (0u32, 1f32.to_bits() + 1);
+            while lo < hi {
+                let mid = lo + (hi - lo) / 2;
+                if encode(f32::from_bits(mid)) as usize > k { hi = mid } else { lo = mid + 1 }
+            }
+            // Ningún valor llega a k + 1 (a 1.0 le corresponde 254)
+            if lo > 1f32.to_bits() { f32::INFINITY } else { f32::from_bits(lo) }
+        });
+        let start = (0..=1f32.to_bits() >> 16)
+            .map(|b| steps.partition_point(|&t| t <= f32::from_bits(b << 16)) as u8)
+            .collect();
+        SrgbEncoder { steps, start }
+    });
+    if c.is_nan() || c <= 0.0 {
+        return 0;
+    }
+    let c = c.min(1.0);
+    let mut k = enc.start[(c.to_bits() >> 16) as usize] as usize;
+    while k < 255 && enc.steps[k] <= c {
+        k += 1;
+    }
+    k as u8
+}
+
+/// Coordenada entera de texel dentro de [0, n) según el modo de repetición.
+fn wrap_coord(i: i64, n: i64, wrap: Wrap) -> usize {
+    (match wrap {
+        Wrap::Clamp => i.clamp(0, n - 1),
+        Wrap::Repeat => i.rem_euclid(n),
+        Wrap::Mirror => {
+            let m = i.rem_euclid(2 * n);
+            if m < n { m } else { 2 * n - 1 - m }
+        }
+    }) as usize
 }
 
 impl TextureCPU {
-    pub fn from_file(path: &str) -> Self {
-        let img = Image::load_image(path).expect("No se pudo cargar textura");
-        let w = img.width() as u32;
-        let h = img.height() as u32;
+    /// Textura de 8 bits por canal con su cadena de mips. `data` debe tener
+    /// exactamente `w * h` texels de `format`. Los formatos de color se
+    /// toman como sRGB; `R8`, como lineal.
+    pub fn new(w: u32, h: u32, format: Format, data: Vec<u8>) -> Result<Self, String> {
+        if format == Format::RgbF32 {
+            return Err("RgbF32 se crea con TextureCPU::new_hdr".to_string());
+        }
+        let expected = w as usize * h as usize * format.channels();
+        if w == 0 || h == 0 || data.len() != expected {
+            return Err(format!("textura {}x{} {:?}: {} bytes, se esperaban {}", w, h, format, data.len(), expected));
+        }
+        Ok(Self::with_level(w, h, format, Texels::U8(data)))
+    }
+
+    /// Textura HDR a partir de colores lineales en coma flotante.
+    pub fn new_hdr(w: u32, h: u32, data: Vec<Vec3>) -> Result<Self, String> {
+        if w == 0 || h == 0 || data.len() != w as usize * h as usize {
+            return Err(format!("textura HDR {}x{}: {} texels", w, h, data.len()));
+        }
+        Ok(Self::with_level(w, h, Format::RgbF32, Texels::F32(data)))
+    }
 
-        let mut data = Vec::with_capacity((w * h * 3) as usize);
+    fn with_level(w: u32, h: u32, format: Format, texels: Texels) -> Self {
+        let mut tex = Self {
+            w,
+            h,
+            format,
+            srgb: matches!(format, Format::Rgb8 | Format::Rgba8),
+            filter: Filter::default(),
+            wrap: Wrap::default(),
+            levels: vec![Level { w, h, texels }],
+        };
+        tex.build_mips();
+        tex
+    }
 
-        // Image::data() retorna RGBA en raylib 5.5.1
-        let pixels: &[u8] = unsafe {
-            // img.data() returns a raw pointer to bytes (c_void); cast to *const u8 and create a slice
-            std::slice::from_raw_parts(img.data() as *const u8, (w * h * 4) as usize)
+    /// Lee una imagen validando su formato; un archivo dañado es un error, no
+    /// un pánico. Los `.hdr` (Radiance) se leen en coma flotante; el resto con
+    /// raylib, conservando uno, tres o cuatro canales según la imagen.
+    pub fn load(path: &str) -> Result<Self, String> {
+        let is_hdr = Path::new(path).extension().is_some_and(|e| e.eq_ignore_ascii_case("hdr"));
+        if is_hdr {
+            let bytes = fs::read(path).map_err(|e| format!("No se pudo cargar {}: {}", path, e))?;
+            let (w, h, data) = parse_radiance(&bytes).map_err(|e| format!("{}: {}", path, e))?;
+            return Self::new_hdr(w, h, data).map_err(|e| format!("{}: {}", path, e));
+        }
+
+        let img = Image::load_image(path).map_err(|e| format!("No se pudo cargar {}: {}", path, e))?;
+        let (w, h) = (img.width().max(0) as u32, img.height().max(0) as u32);
+        let format = match img.format() {
+            PixelFormat::PIXELFORMAT_UNCOMPRESSED_GRAYSCALE => Format::R8,
+            PixelFormat::PIXELFORMAT_UNCOMPRESSED_R5G6B5
+            | PixelFormat::PIXELFORMAT_UNCOMPRESSED_R8G8B8
+            | PixelFormat::PIXELFORMAT_UNCOMPRESSED_R32G32B32
+            | PixelFormat::PIXELFORMAT_UNCOMPRESSED_R16G16B16 => Format::Rgb8,
+            _ => Format::Rgba8,
         };
-        for y in 0..h {
-            for x in 0..w {
-                // each pixel is 4 bytes: R,G,B,A
-                let idx = (((y * w) + x) * 4) as usize;
-                data.push(pixels[idx]);
-                data.push(pixels[idx + 1]);
-                data.push(pixels[idx + 2]);
+        // raylib convierte cualquier formato de origen a RGBA8
+        let colors = img.get_image_data();
+        if colors.len() != w as usize * h as usize {
+            return Err(format!("{}: {} píxeles para una imagen {}x{}", path, colors.len(), w, h));
+        }
+        let channels = format.channels();
+        let data = colors.iter().flat_map(|c| [c.r, c.g, c.b, c.a].into_iter().take(channels)).collect();
+        Self::new(w, h, format, data).map_err(|e| format!("{}: {}", path, e))
+    }
+
+    pub fn format(&self) -> Format {
+        self.format
+    }
+
+    /// Marca los canales de color como lineales (o sRGB) y rehace los mips
+    /// en consecuencia. No afecta a las texturas HDR.
+    pub fn set_srgb(&mut self, srgb: bool) {
+        if self.format != Format::RgbF32 && self.srgb != srgb {
+            self.srgb = srgb;
+            self.build_mips();
+        }
+    }
+
+    /// Texels del nivel 0, tal como se crearon.
+    pub(crate) fn base_texels(&self) -> &Texels {
+        &self.levels[0].texels
+    }
+
+    /// Número de niveles, contando el 0.
+    #[cfg(test)]
+    pub fn mip_levels(&self) -> usize {
+        self.levels.len()
+    }
+
+    /// Cada nivel es la mitad del anterior (promediando 2x2 en espacio
+    /// lineal) hasta llegar a 1x1.
+    fn build_mips(&mut self) {
+        self.levels.truncate(1);
+        let (mut w, mut h) = (self.w, self.h);
+        while w > 1 || h > 1 {
+            let (nw, nh) = ((w / 2).max(1), (h / 2).max(1));
+            let prev = self.levels.last().unwrap();
+            let mut texels = match prev.texels {
+                Texels::U8(_) => Texels::U8(Vec::with_capacity(nw as usize * nh as usize * self.format.channels())),
+                Texels::F32(_) => Texels::F32(Vec::with_capacity(nw as usize * nh as usize)),
+            };
+            for y in 0..nh {
+                for x in 0..nw {
+                    let mut sum = Vec4::ZERO;
+                    for (dx, dy) in [(0, 0), (1, 0), (0, 1), (1, 1)] {
+                        let sx = (2 * x + dx).min(w - 1);
+                        let sy = (2 * y + dy).min(h - 1);
+                        sum += self.fetch(prev, (sy * w + sx) as usize);
+                    }
+                    self.push_texel(&mut texels, sum * 0.25);
+                }
             }
+            self.levels.push(Level { w: nw, h: nh, texels });
+            (w, h) = (nw, nh);
+        }
+    }
+
+    fn decode(&self, v: u8) -> f32 {
+        if self.srgb { srgb_table()[v as usize] } else { v as f32 / 255.0 }
+    }
+
+    fn encode(&self, v: f32) -> u8 {
+        let v = if self.srgb { linear_to_srgb(v) } else { v };
+        (v.clamp(0.0, 1.0) * 255.0).round() as u8
+    }
+
+    /// Texel `i` de un nivel como color lineal con alfa.
+    fn fetch(&self, level: &Level, i: usize) -> Vec4 {
+        match &level.texels {
+            Texels::F32(data) => data[i].extend(1.0),
+            Texels::U8(data) => match self.format {
+                Format::R8 => Vec3::splat(self.decode(data[i])).extend(1.0),
+                Format::Rgba8 => {
+                    let p = &data[i * 4..i * 4 + 4];
+                    Vec4::new(self.decode(p[0]), self.decode(p[1]), self.decode(p[2]), p[3] as f32 / 255.0)
+                }
+                _ => {
+                    let p = &data[i * 3..i * 3 + 3];
+                    Vec4::new(self.decode(p[0]), self.decode(p[1]), self.decode(p[2]), 1.0)
+                }
+            },
         }
+    }
+
+    fn push_texel(&self, texels: &mut Texels, c: Vec4) {
+        match texels {
+            Texels::F32(data) => data.push(c.truncate()),
+            Texels::U8(data) => match self.format {
+                Format::R8 => data.push(self.encode(c.x)),
+                Format::Rgba8 => data.extend([self.encode(c.x), self.encode(c.y), self.encode(c.z), (c.w.clamp(0.0, 1.0) * 255.0).round() as u8]),
+                _ => data.extend([self.encode(c.x), self.encode(c.y), self.encode(c.z)]),
+            },
+        }
+    }
 
-        Self { w, h, data }
+    fn texel(&self, level: &Level, x: i64, y: i64) -> Vec4 {
+        let x = wrap_coord(x, level.w as i64, self.wrap);
+        let y = wrap_coord(y, level.h as i64, self.wrap);
+        self.fetch(level, y * level.w as usize + x)
     }
 
+    fn bilinear(&self, level: &Level, u: f32, v: f32) -> Vec4 {
+        // Centros de texel en (i + 0.5) / w. NaN o infinito no tienen texel:
+        // se toma el origen
+        let finite = |c: f32| if c.is_finite() { c } else { 0.0 };
+        let x = finite(u * level.w as f32 - 0.5);
+        let y = finite(v * level.h as f32 - 0.5);
+        let (x0, y0) = (x.floor(), y.floor());
+        let (fx, fy) = (x - x0, y - y0);
+        // Fuera del rango de i64 el `as` satura: el vecino no puede desbordar
+        let (x0, y0) = (x0 as i64, y0 as i64);
+        let (w, h) = (level.w as i64, level.h as i64);
+        let [xa, xb] = [x0, x0.saturating_add(1)].map(|x| wrap_coord(x, w, self.wrap));
+        let [ya, yb] = [y0, y0.saturating_add(1)].map(|y| wrap_coord(y, h, self.wrap) * level.w as usize);
+        let top = self.fetch(level, ya + xa).lerp(self.fetch(level, ya + xb), fx);
+        let bottom = self.fetch(level, yb + xa).lerp(self.fetch(level, yb + xb), fx);
+        top.lerp(bottom, fy)
+    }
+
+    /// Color lineal en (u, v) con el filtro y la repetición de la textura, en
+    /// el nivel de detalle más fino. Cualquier `u`, `v` es válido.
     pub fn sample(&self, u: f32, v: f32) -> Vec3 {
-        let uu = (u * (self.w - 1) as f32) as usize;
-        let vv = (v * (self.h - 1) as f32) as usize;
-        let idx = (vv * self.w as usize + uu) * 3;
-
-        Vec3::new(
-            self.data[idx] as f32 / 255.0,
-            self.data[idx + 1] as f32 / 255.0,
-            self.data[idx + 2] as f32 / 255.0,
-        )
+        self.sample_lod(u, v, 0.0)
+    }
+
+    /// Como `sample`, con el nivel de mip `lod` (log2 de texels por pixel).
+    pub fn sample_lod(&self, u: f32, v: f32, lod: f32) -> Vec3 {
+        self.sample_rgba_lod(u, v, lod).truncate()
+    }
+
+    /// Color lineal y alfa (1 en los formatos sin alfa). Solo
+    /// `Filter::Trilinear` usa los mips; los otros filtros leen el nivel 0.
+    pub fn sample_rgba_lod(&self, u: f32, v: f32, lod: f32) -> Vec4 {
+        let base = &self.levels[0];
+        match self.filter {
+            Filter::Nearest => self.texel(base, (u * base.w as f32).floor() as i64, (v * base.h as f32).floor() as i64),
+            Filter::Bilinear => self.bilinear(base, u, v),
+            Filter::Trilinear => {
+                let last = self.levels.len() - 1;
+                let lod = if lod.is_finite() { lod.clamp(0.0, last as f32) } else { 0.0 };
+                let l0 = lod.floor() as usize;
+                let a = self.bilinear(&self.levels[l0], u, v);
+                // Ampliando (lod <= 0) el segundo nivel tendría peso 0
+                let t = lod - l0 as f32;
+                if l0 == last || t == 0.0 {
+                    return a;
+                }
+                a.lerp(self.bilinear(&self.levels[l0 + 1], u, v), t)
+            }
+        }
+    }
+}
+
+/// Lee una imagen Radiance (`.hdr`, RGBE) y devuelve ancho, alto y colores
+/// lineales. Acepta scanlines planas y con RLE "nuevo"; solo la orientación
+/// estándar (`-Y alto +X ancho`).
+fn parse_radiance(bytes: &[u8]) -> Result<(u32, u32, Vec<Vec3>), String> {
+    let mut pos = 0;
+    let mut next_line = || -> Result<&[u8], String> {
+        let rest = &bytes[pos..];
+        let end = rest.iter().position(|&b| b == b'\n').ok_or("cabecera incompleta")?;
+        pos += end + 1;
+        Ok(&rest[..end])
+    };
+
+    let magic = next_line()?;
+    if !magic.starts_with(b"#?RADIANCE") && !magic.starts_with(b"#?RGBE") {
+        return Err("no es un archivo Radiance".to_string());
+    }
+    loop {
+        let line = next_line()?;
+        if line.is_empty() {
+            break;
+        }
+        if let Some(format) = line.strip_prefix(b"FORMAT=")
+            && format != b"32-bit_rle_rgbe"
+        {
+            return Err(format!("formato {} no soportado", String::from_utf8_lossy(format)));
+        }
+    }
+    let res = String::from_utf8_lossy(next_line()?).into_owned();
+    let (h, w) = match res.split_whitespace().collect::<Vec<_>>()[..] {
+        ["-Y", h, "+X", w] => (h.parse::<u32>().ok(), w.parse::<u32>().ok()),
+        _ => (None, None),
+    };
+    let (Some(h), Some(w)) = (h, w) else {
+        return Err(format!("resolución '{}' no soportada", res));
+    };
+
+    // Antes de reservar memoria: la resolución no puede pedir más scanlines
+    // de las que caben en lo que queda del archivo (una con RLE ocupa al menos
+    // su cabecera y un tramo por canal cada 128 texels; una plana, 4 bytes por texel)
+    let truncated = || "datos de imagen incompletos".to_string();
+    let rle_width = (8..0x8000).contains(&w);
+    let min_scanline = if rle_width { 4 + 8 * (w as u64).div_ceil(128) } else { 4 * w as u64 };
+    if w == 0 || h == 0 {
+        return Err(format!("resolución '{}' vacía", res));
+    }
+    if min_scanline.saturating_mul(h as u64) > (bytes.len() - pos) as u64 {
+        return Err(truncated());
+    }
+
+    let mut data = bytes[pos..].iter().copied();
+    let mut out = Vec::with_capacity(w as usize * h as usize);
+    let mut scan = vec![[0u8; 4]; w as usize];
+    for _ in 0..h {
+        let head: Vec<u8> = data.by_ref().take(4).collect();
+        if head.len() < 4 {
+            return Err(truncated());
+        }
+        let rle = rle_width && head[0] == 2 && head[1] == 2 && head[2] & 0x80 == 0;
+        if rle {
+            if ((head[2] as u32) << 8 | head[3] as u32) != w {
+                return Err("longitud de scanline incorrecta".to_string());
+            }
+            // Cada canal por separado: tramos repetidos (n > 128) o literales
+            for c in 0..4 {
+                let mut x = 0;
+                while x < w as usize {
+                    let n = data.next().ok_or_else(truncated)? as usize;
+                    let (count, run) = if n > 128 { (n - 128, true) } else { (n, false) };
+                    if count == 0 || x + count > w as usize {
+                        return Err("tramo RLE inválido".to_string());
+                    }
+                    let value = if run { data.next().ok_or_else(truncated)? } else { 0 };
+                    for px in &mut scan[x..x + count] {
+                        px[c] = if run { value } else { data.next().ok_or_else(truncated)? };
+                    }
+                    x += count;
+                }
+            }
+        } else {
+            scan[0].copy_from_slice(&head);
+            for px in scan.iter_mut().skip(1) {
+                for b in px.iter_mut() {
+                    *b = data.next().ok_or_else(truncated)?;
+                }
+            }
+        }
+        out.extend(scan.iter().map(|&[r, g, b, e]| {
+            if e == 0 {
+                Vec3::ZERO
+            } else {
+                Vec3::new(r as f32, g as f32, b as f32) * 2f32.powi(e as i32 - 136)
+            }
+        }));
+    }
+    Ok((w, h, out))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// 2x2: negro, blanco / blanco, negro
+    fn checker() -> TextureCPU {
+        TextureCPU::new(2, 2, Format::Rgb8, vec![0, 0, 0, 255, 255, 255, 255, 255, 255, 0, 0, 0]).unwrap()
+    }
+
+    #[test]
+    fn sampling_outside_unit_range_wraps() {
+        let mut tex = checker();
+        let odd = [(-3.7, 0.2), (1.0, 1.0), (12.5, -0.01), (f32::NAN, f32::INFINITY), (f32::NEG_INFINITY, 1e30), (-1e30, f32::MAX)];
+        for filter in [Filter::Nearest, Filter::Bilinear, Filter::Trilinear] {
+            tex.filter = filter;
+            for wrap in [Wrap::Clamp, Wrap::Repeat, Wrap::Mirror] {
+                tex.wrap = wrap;
+                for (u, v) in odd {
+                    assert!(tex.sample_lod(u, v, 0.5).is_finite(), "{:?} {:?} ({}, {})", filter, wrap, u, v);
+                }
+            }
+        }
+        tex.filter = Filter::Nearest;
+        tex.wrap = Wrap::Repeat;
+        assert_eq!(tex.sample(1.25, 0.25), tex.sample(0.25, 0.25));
+        tex.wrap = Wrap::Clamp;
+        assert_eq!(tex.sample(5.0, 0.25), Vec3::ONE);
+        tex.wrap = Wrap::Mirror;
+        assert_eq!(tex.sample(1.25, 0.25), Vec3::ONE);
+    }
+
+    #[test]
+    fn filtering_averages_in_linear_space() {
+        let mut tex = checker();
+        tex.wrap = Wrap::Clamp;
+        // Centro exacto: media lineal de dos blancos y dos negros
+        assert!((tex.sample(0.5, 0.5) - Vec3::splat(0.5)).abs().max_element() < 1e-6);
+
+        tex.filter = Filter::Trilinear;
+        assert_eq!(tex.mip_levels(), 2);
+        // El mip 1x1 guarda 0.5 lineal codificado en sRGB (188), no 128
+        assert!(matches!(&tex.levels[1].texels, Texels::U8(d) if *d == vec![188, 188, 188]));
+        let far = tex.sample_lod(0.1, 0.1, 5.0);
+        assert!((far - Vec3::splat(0.5)).abs().max_element() < 0.01);
+    }
+
+    #[test]
+    fn srgb_table_matches_powf() {
+        for i in 0..=200_000 {
+            let c = (i as f32 / 200_000.0).powi(3);
+            assert_eq!(linear_to_srgb_u8(c), (linear_to_srgb(c) * 255.0) as u8, "c = {}", c);
+        }
+        assert_eq!(linear_to_srgb_u8(-1.0), 0);
+        assert_eq!(linear_to_srgb_u8(7.0), (linear_to_srgb(7.0) * 255.0) as u8);
+        assert_eq!(linear_to_srgb_u8(f32::NAN), 0);
+    }
+
+    #[test]
+    fn formats_keep_alpha_and_reject_bad_sizes() {
+        assert!(TextureCPU::new(2, 2, Format::Rgba8, vec![0; 12]).is_err());
+        assert!(TextureCPU::new(0, 4, Format::R8, Vec::new()).is_err());
+
+        let mut rgba = TextureCPU::new(1, 1, Format::Rgba8, vec![255, 0, 0, 64]).unwrap();
+        rgba.filter = Filter::Nearest;
+        assert_eq!(rgba.sample_rgba_lod(0.5, 0.5, 0.0), Vec4::new(1.0, 0.0, 0.0, 64.0 / 255.0));
+
+        let mask = TextureCPU::new(2, 1, Format::R8, vec![0, 255]).unwrap();
+        assert!(!mask.srgb);
+        assert_eq!(mask.sample_rgba_lod(0.75, 0.5, 0.0), Vec4::ONE);
+        assert!((mask.sample(0.5, 0.5).x - 0.5).abs() < 1e-6);
+    }
+
+    #[test]
+    fn radiance_flat_and_rle() {
+        let mut file = b"#?RADIANCE\nFORMAT=32-bit_rle_rgbe\n\n-Y 2 +X 8\n".to_vec();
+        // Fila 0 plana: 8 texels (128, 64, 0) con exponente 129 -> (1, 0.5, 0)
+        for _ in 0..8 {
+            file.extend([128, 64, 0, 129]);
+        }
+        // Fila 1 con RLE: R=255 repetido, G y B literales a 0, E repetido (136)
+        file.extend([2, 2, 0, 8]);
+        file.extend([128 + 8, 255]);
+        file.push(8);
+        file.extend([0; 8]);
+        file.extend([128 + 8, 0]);
+        file.extend([128 + 8, 136]);
+        let (w, h, data) = parse_radiance(&file).unwrap();
+        assert_eq!((w, h), (8, 2));
+        assert_eq!(data[3], Vec3::new(1.0, 0.5, 0.0));
+        assert_eq!(data[12], Vec3::new(255.0, 0.0, 0.0));
+
+        let hdr = TextureCPU::new_hdr(w, h, data).unwrap();
+        assert_eq!(hdr.format(), Format::RgbF32);
+        assert!(hdr.sample(0.5, 0.9).x > 1.0);
+
+        assert!(parse_radiance(&file[..file.len() - 3]).is_err());
+        assert!(parse_radiance(b"P6\n2 2\n").is_err());
+        // Una cabecera que promete más de lo que trae no llega a reservar memoria
+        let mut huge = b"#?RADIANCE\n\n-Y 4000000000 +X 4000000000\n".to_vec();
+        huge.extend([2, 2, 0, 8]);
+        assert!(parse_radiance(&huge).is_err());
+        assert!(parse_radiance(b"#?RADIANCE\n\n-Y 3000000 +X 2\n\0\0\0\0").is_err());
     }
 }
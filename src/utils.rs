@@ -10,6 +10,50 @@ pub fn clamp01(x: f32) -> f32 {
     else { x }
 }
 
+pub fn smoothstep(edge0: f32, edge1: f32, x: f32) -> f32 {
+    let t = clamp01((x - edge0) / (edge1 - edge0));
+    t * t * (3.0 - 2.0 * t)
+}
+
+fn hash3(x: i32, y: i32, z: i32) -> f32 {
+    let mut h = (x as u32).wrapping_mul(0x8da6_b343)
+        ^ (y as u32).wrapping_mul(0xd816_3841)
+        ^ (z as u32).wrapping_mul(0xcb1a_b31f);
+    h = (h ^ (h >> 13)).wrapping_mul(0x5bd1_e995);
+    h ^= h >> 15;
+    (h & 0x00ff_ffff) as f32 / 0x00ff_ffff as f32
+}
+
+/// Value noise 3D suavizado, rango [0, 1].
+pub fn noise3(p: Vec3) -> f32 {
+    let i = p.floor();
+    let f = p - i;
+    let u = f * f * (Vec3::splat(3.0) - 2.0 * f);
+    let (x, y, z) = (i.x as i32, i.y as i32, i.z as i32);
+
+    let lerp = |a: f32, b: f32, t: f32| a + (b - a) * t;
+    let x00 = lerp(hash3(x, y, z), hash3(x + 1, y, z), u.x);
+    let x10 = lerp(hash3(x, y + 1, z), hash3(x + 1, y + 1, z), u.x);
+    let x01 = lerp(hash3(x, y, z + 1), hash3(x + 1, y, z + 1), u.x);
+    let x11 = lerp(hash3(x, y + 1, z + 1), hash3(x + 1, y + 1, z + 1), u.x);
+    lerp(lerp(x00, x10, u.y), lerp(x01, x11, u.y), u.z)
+}
+
+/// Fractal (fBm) de `octaves` capas de `noise3`, rango [0, 1].
+pub fn fbm3(p: Vec3, octaves: u32) -> f32 {
+    let mut sum = 0.0;
+    let mut amp = 0.5;
+    let mut norm = 0.0;
+    let mut q = p;
+    for _ in 0..octaves {
+        sum += noise3(q) * amp;
+        norm += amp;
+        amp *= 0.5;
+        q *= 2.03;
+    }
+    sum / norm
+}
+
 pub fn look_at(eye: Vec3, target: Vec3, up: Vec3) -> Mat4 {
     Mat4::look_at_rh(eye, target, up)
 }
@@ -17,3 +61,93 @@ pub fn look_at(eye: Vec3, target: Vec3, up: Vec3) -> Mat4 {
 pub fn perspective(fov_y_rad: f32, aspect: f32, near: f32, far: f32) -> Mat4 {
     Mat4::perspective_rh(fov_y_rad, aspect, near, far)
 }
+
+/// Small deterministic PRNG (SplitMix64). Same seed -> same sequence on every
+/// platform, so generated content can be reproduced from its seed.
+#[derive(Clone)]
+pub struct Rng {
+    state: u64,
+}
+
+impl Rng {
+    pub fn new(seed: u64) -> Self {
+        Self { state: seed }
+    }
+
+    pub fn next_u64(&mut self) -> u64 {
+        self.state = self.state.wrapping_add(0x9E37_79B9_7F4A_7C15);
+        let mut z = self.state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        z ^ (z >> 31)
+    }
+
+    /// Uniform in [0, 1)
+    pub fn next_f32(&mut self) -> f32 {
+        (self.next_u64() >> 40) as f32 / (1u64 << 24) as f32
+    }
+
+    /// Uniform in [min, max)
+    pub fn range(&mut self, min: f32, max: f32) -> f32 {
+        min + (max - min) * self.next_f32()
+    }
+
+    /// Uniform integer in [min, max]
+    pub fn range_usize(&mut self, min: usize, max: usize) -> usize {
+        min + (self.next_u64() % (max - min + 1) as u64) as usize
+    }
+
+    pub fn chance(&mut self, p: f32) -> bool {
+        self.next_f32() < p
+    }
+}
+
+/// Scratch directory for tests that need real files. Each call gets its own
+/// path, so tests running in parallel never share fixtures, and the directory
+/// is removed on drop even when the test panics.
+#[cfg(test)]
+pub struct TempDir {
+    path: std::path::PathBuf,
+}
+
+#[cfg(test)]
+impl TempDir {
+    pub fn new(name: &str) -> Self {
+        use std::sync::atomic::{AtomicU32, Ordering};
+        static NEXT: AtomicU32 = AtomicU32::new(0);
+        let path = std::env::temp_dir().join(format!(
+            "spacetravel_{}_{}_{}",
+            name,
+            std::process::id(),
+            NEXT.fetch_add(1, Ordering::Relaxed)
+        ));
+        std::fs::create_dir_all(&path).unwrap();
+        Self { path }
+    }
+
+    pub fn path(&self) -> &std::path::Path {
+        &self.path
+    }
+
+    pub fn join(&self, rel: impl AsRef<std::path::Path>) -> std::path::PathBuf {
+        self.path.join(rel)
+    }
+
+    /// Writes `contents` to `rel`, creating parent directories, and returns
+    /// the full path
+    pub fn write(&self, rel: impl AsRef<std::path::Path>, contents: impl AsRef<[u8]>) -> std::path::PathBuf {
+        let file = self.path.join(rel);
+        if let Some(parent) = file.parent() {
+            std::fs::create_dir_all(parent).unwrap();
+        }
+        std::fs::write(&file, contents).unwrap();
+        file
+    }
+}
+
+#[cfg(test)]
+impl Drop for TempDir {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_dir_all(&self.path);
+    }
+}